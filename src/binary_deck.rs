@@ -0,0 +1,205 @@
+// Column-binary decks: any file punched 12 bits to a column, the way
+// binary programs went onto cards. Every card has 7-9 punched in column 1,
+// the traditional mark of a binary card, and carries its own number and
+// checksum so a dropped or damaged card is caught on restore.
+//
+// Card layout, one 12-bit word per column:
+//   column 1      card type: 7-9 for data, 7-8-9 for the trailer
+//   columns 2-3   card number, counting from 1
+//   columns 4-6   CRC-32 of the card number and payload
+//   columns 7-80  payload, 111 bytes packed three to every two columns
+//
+// The trailer card follows the data cards. Its payload holds the file
+// length (8 bytes), the number of data cards (4 bytes) and the SHA-256 of
+// the file (32 bytes), all big-endian.
+
+use sha2::{Digest, Sha256};
+
+use crate::card_image::{column_bits, column_punches};
+use crate::{PunchCard, COLUMNS};
+
+/// Column 1 of a data card: 7-9
+const DATA_CARD: u16 = 0x005;
+/// Column 1 of the trailer card: 7-8-9
+const TRAILER_CARD: u16 = 0x007;
+
+const HEADER_COLUMNS: usize = 6;
+/// Payload bytes per card: two 12-bit columns hold three bytes
+pub const PAYLOAD_BYTES: usize = (COLUMNS - HEADER_COLUMNS) / 2 * 3;
+
+/// Whether a card is column binary, going by column 1: exactly 7-9, or
+/// 7-8-9 on the trailer. Other punches with them, such as 12-7-9, are a
+/// character and not the binary mark.
+pub fn is_binary_card(columns: &[Vec<usize>]) -> bool {
+    columns
+        .first()
+        .is_some_and(|punches| matches!(column_bits(punches), DATA_CARD | TRAILER_CARD))
+}
+
+/// CRC over the card number and payload
+fn card_crc(number: u32, payload: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&number.to_be_bytes()[1..]);
+    hasher.update(payload);
+    hasher.finalize()
+}
+
+/// Build one card from its type, number and payload
+fn punch_card(card_type: u16, number: u32, payload: &[u8]) -> PunchCard {
+    let mut padded = payload.to_vec();
+    padded.resize(PAYLOAD_BYTES, 0);
+    let crc = card_crc(number, &padded);
+
+    let mut words = vec![
+        card_type,
+        (number >> 12) as u16 & 0xFFF,
+        number as u16 & 0xFFF,
+        (crc >> 24) as u16,
+        (crc >> 12) as u16 & 0xFFF,
+        crc as u16 & 0xFFF,
+    ];
+    for bytes in padded.chunks(3) {
+        words.push((bytes[0] as u16) << 4 | (bytes[1] as u16) >> 4);
+        words.push((bytes[1] as u16 & 0x0F) << 8 | bytes[2] as u16);
+    }
+
+    PunchCard {
+        columns: words.into_iter().map(column_punches).collect(),
+    }
+}
+
+/// Punch a file as column-binary data cards followed by a trailer card
+pub fn encode(data: &[u8]) -> Vec<PunchCard> {
+    let mut cards: Vec<PunchCard> = data
+        .chunks(PAYLOAD_BYTES)
+        .enumerate()
+        .map(|(idx, payload)| punch_card(DATA_CARD, idx as u32 + 1, payload))
+        .collect();
+
+    let data_cards = cards.len() as u32;
+    let mut trailer = Vec::with_capacity(PAYLOAD_BYTES);
+    trailer.extend_from_slice(&(data.len() as u64).to_be_bytes());
+    trailer.extend_from_slice(&data_cards.to_be_bytes());
+    trailer.extend_from_slice(&Sha256::digest(data));
+    cards.push(punch_card(TRAILER_CARD, data_cards + 1, &trailer));
+
+    cards
+}
+
+/// Read one card back into its type, number and payload, checking its CRC
+fn read_card(card: &PunchCard, position: usize) -> Result<(u16, u32, Vec<u8>), String> {
+    let words: Vec<u16> = card.columns.iter().map(|punches| column_bits(punches)).collect();
+    let card_type = words[0];
+    if card_type != DATA_CARD && card_type != TRAILER_CARD {
+        return Err(format!("card {} is not a column-binary card (no 7-9 punch in column 1)", position));
+    }
+
+    let number = (words[1] as u32) << 12 | words[2] as u32;
+    let crc = (words[3] as u32) << 24 | (words[4] as u32) << 12 | words[5] as u32;
+
+    let mut payload = Vec::with_capacity(PAYLOAD_BYTES);
+    for pair in words[HEADER_COLUMNS..].chunks(2) {
+        payload.push((pair[0] >> 4) as u8);
+        payload.push(((pair[0] & 0x0F) << 4 | pair[1] >> 8) as u8);
+        payload.push(pair[1] as u8);
+    }
+
+    if card_crc(number, &payload) != crc {
+        return Err(format!("card {} (numbered {}) fails its CRC check", position, number));
+    }
+    Ok((card_type, number, payload))
+}
+
+/// Rebuild the file from its cards, checking every card's number and CRC
+/// and the trailer's length and hash
+pub fn decode(cards: &[PunchCard]) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+
+    for (idx, card) in cards.iter().enumerate() {
+        let position = idx + 1;
+        let (card_type, number, payload) = read_card(card, position)?;
+        if number as usize != position {
+            return Err(format!(
+                "card {} is numbered {}; cards are missing or out of order",
+                position, number
+            ));
+        }
+
+        if card_type == DATA_CARD {
+            data.extend_from_slice(&payload);
+            continue;
+        }
+
+        if position != cards.len() {
+            return Err(format!("trailer card {} is followed by {} more cards", position, cards.len() - position));
+        }
+        let length = u64::from_be_bytes(payload[0..8].try_into().unwrap()) as usize;
+        let data_cards = u32::from_be_bytes(payload[8..12].try_into().unwrap()) as usize;
+        if data_cards != idx || length.div_ceil(PAYLOAD_BYTES) != data_cards {
+            return Err(format!(
+                "trailer records {} bytes on {} cards, but the deck has {} data cards",
+                length, data_cards, idx
+            ));
+        }
+        data.truncate(length);
+        if Sha256::digest(&data)[..] != payload[12..44] {
+            return Err("SHA-256 of the restored data does not match the trailer".to_string());
+        }
+        return Ok(data);
+    }
+
+    Err("deck has no trailer card; cards are missing from the end".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_mark_is_exactly_7_9() {
+        // Rows are 12, 11, 0, 1-9 from index 0
+        let card = |first: Vec<usize>| vec![first, vec![]];
+        assert!(is_binary_card(&card(vec![9, 11])));
+        assert!(is_binary_card(&card(vec![9, 10, 11])));
+        assert!(!is_binary_card(&card(vec![0, 9, 11])));
+        assert!(!is_binary_card(&card(vec![2, 9, 11])));
+        assert!(!is_binary_card(&card(vec![9])));
+        assert!(!is_binary_card(&[]));
+    }
+
+    #[test]
+    fn encode_and_restore() {
+        for length in [0, 1, PAYLOAD_BYTES - 1, PAYLOAD_BYTES, PAYLOAD_BYTES * 3 + 7] {
+            let data: Vec<u8> = (0..length).map(|idx| (idx * 37 % 256) as u8).collect();
+            let cards = encode(&data);
+            assert_eq!(cards.len(), length.div_ceil(PAYLOAD_BYTES) + 1);
+            assert!(cards.iter().all(|card| card.columns.len() == COLUMNS && is_binary_card(&card.columns)));
+            assert_eq!(decode(&cards).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn restore_catches_damaged_and_missing_cards() {
+        let data: Vec<u8> = (0..PAYLOAD_BYTES * 2).map(|idx| idx as u8).collect();
+        let mut cards = encode(&data);
+
+        let mut swapped = encode(&data);
+        swapped.swap(0, 1);
+        let Err(err) = decode(&swapped) else { panic!("swapped cards restored") };
+        assert!(err.contains("out of order"), "{}", err);
+
+        let Err(err) = decode(&cards[..2]) else { panic!("deck without a trailer restored") };
+        assert!(err.contains("no trailer"), "{}", err);
+
+        // One hole too many or too few in the payload
+        let column = &mut cards[1].columns[40];
+        match column.iter().position(|&row| row == 0) {
+            Some(idx) => {
+                column.remove(idx);
+            }
+            None => column.insert(0, 0),
+        }
+        let Err(err) = decode(&cards) else { panic!("damaged card restored") };
+        assert!(err.contains("CRC"), "{}", err);
+    }
+}
@@ -0,0 +1,304 @@
+// Where the punch grid lies on a template image. Each template may carry
+// its own calibration in a sidecar file beside it, the image's name with a
+// `.toml` extension, holding the six measurements in template pixels as
+// TOML `key = value` lines, with `#` starting a comment:
+//
+//   first_punch_x = 28.5     # left edge of column 1
+//   first_punch_y = 27.0     # top edge of row 12
+//   column_spacing = 9.327
+//   row_spacing = 26.667
+//   punch_width = 5.9
+//   punch_height = 13.33
+//
+// The bundled template ships with its file. A template without one is
+// taken to be laid out like the bundled template. The `calibrate` command
+// measures a new template by finding the digits 0 to 9 printed in every
+// column and writes the file.
+
+use std::path::{Path, PathBuf};
+
+use image::RgbImage;
+
+use crate::{COLUMNS, COLUMN_SPACING, FIRST_PUNCH_X, FIRST_PUNCH_Y, PUNCH_HEIGHT_PX, PUNCH_WIDTH_PX, ROWS, ROW_SPACING};
+
+/// Rows printed with digits: 0 to 9, below the 12 and 11 rows
+const DIGIT_ROWS: usize = 10;
+const FIRST_DIGIT_ROW: usize = 2;
+
+/// A punched hole is 0.055 by 0.125 inch, in columns 0.087 inch apart and
+/// rows 0.25 inch apart
+const HOLE_WIDTH_PER_COLUMN: f32 = 0.055 / 0.087;
+const HOLE_HEIGHT_PER_ROW: f32 = 0.125 / 0.25;
+
+/// How much darker than the card stock a pixel must be to count as print
+const INK_CONTRAST: f32 = 60.0;
+
+/// The punch grid of a template, in its pixels
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    /// Top-left corner of the punch in column 1, row 12
+    pub first_punch_x: f32,
+    pub first_punch_y: f32,
+    pub column_spacing: f32,
+    pub row_spacing: f32,
+    pub punch_width: f32,
+    pub punch_height: f32,
+}
+
+impl Calibration {
+    /// The bundled `punchcard_template.png`
+    pub const BUNDLED: Calibration = Calibration {
+        first_punch_x: FIRST_PUNCH_X,
+        first_punch_y: FIRST_PUNCH_Y,
+        column_spacing: COLUMN_SPACING,
+        row_spacing: ROW_SPACING,
+        punch_width: PUNCH_WIDTH_PX,
+        punch_height: PUNCH_HEIGHT_PX,
+    };
+
+    /// Left edge of a column's punches, 0-based
+    pub fn punch_left(&self, col_idx: usize) -> f32 {
+        self.first_punch_x + col_idx as f32 * self.column_spacing
+    }
+
+    /// Top edge of a row's punches, as an index into a column's punches
+    pub fn punch_top(&self, row: usize) -> f32 {
+        self.first_punch_y + row as f32 * self.row_spacing
+    }
+
+    /// A punch's rectangle on a card drawn from an image `image_height`
+    /// pixels high, `scale` units to the pixel, measured up from the card's
+    /// bottom edge: x, y, width, height
+    pub fn punch_rect(&self, col_idx: usize, row: usize, image_height: f32, (scale_x, scale_y): (f32, f32)) -> (f32, f32, f32, f32) {
+        let (width, height) = (self.punch_width * scale_x, self.punch_height * scale_y);
+        // Image rows count down from the top, card units up from the bottom
+        let y = (image_height - self.punch_top(row)) * scale_y - height;
+        (self.punch_left(col_idx) * scale_x, y, width, height)
+    }
+
+    /// Read a sidecar file
+    pub fn parse(text: &str) -> Result<Calibration, String> {
+        let mut values = [None; 6];
+        for (line_num, key, value) in key_values(text)? {
+            let slot = match key {
+                "first_punch_x" => 0,
+                "first_punch_y" => 1,
+                "column_spacing" => 2,
+                "row_spacing" => 3,
+                "punch_width" => 4,
+                "punch_height" => 5,
+                other => return Err(format!("line {}: unknown key {}", line_num, other)),
+            };
+            if value < 0.0 {
+                return Err(format!("line {}: {} is not a number of pixels", line_num, value));
+            }
+            values[slot] = Some(value);
+        }
+
+        let [Some(first_punch_x), Some(first_punch_y), Some(column_spacing), Some(row_spacing), Some(punch_width), Some(punch_height)] =
+            values
+        else {
+            return Err("first_punch_x, first_punch_y, column_spacing, row_spacing, punch_width and punch_height are all needed".to_string());
+        };
+        if punch_width > column_spacing || punch_height > row_spacing {
+            return Err("punches are larger than the spacing between them".to_string());
+        }
+        Ok(Calibration { first_punch_x, first_punch_y, column_spacing, row_spacing, punch_width, punch_height })
+    }
+
+    /// The sidecar file's contents
+    pub fn to_toml(&self) -> String {
+        format!(
+            "# Punch grid of the template, in its pixels\n\
+             first_punch_x = {:.2}\n\
+             first_punch_y = {:.2}\n\
+             column_spacing = {:.3}\n\
+             row_spacing = {:.3}\n\
+             punch_width = {:.2}\n\
+             punch_height = {:.2}\n",
+            self.first_punch_x, self.first_punch_y, self.column_spacing, self.row_spacing, self.punch_width, self.punch_height
+        )
+    }
+
+    /// Whether the whole grid fits on an image of the given size
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        self.first_punch_x >= 0.0
+            && self.first_punch_y >= 0.0
+            && self.punch_left(COLUMNS - 1) + self.punch_width <= width as f32
+            && self.punch_top(ROWS - 1) + self.punch_height <= height as f32
+    }
+}
+
+/// The `key = value` lines of a calibration or printer profile file, with
+/// their line numbers; `#` starts a comment and every value is a number
+pub fn key_values(text: &str) -> Result<Vec<(usize, &str, f32)>, String> {
+    let mut pairs = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", line_idx + 1))?;
+        let value = value
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("line {}: {} is not a number", line_idx + 1, value.trim()))?;
+        pairs.push((line_idx + 1, key.trim(), value));
+    }
+    Ok(pairs)
+}
+
+/// Where a template's calibration is kept: beside it, as `.toml`
+pub fn sidecar_path(template_path: &str) -> PathBuf {
+    Path::new(template_path).with_extension("toml")
+}
+
+/// The calibration for a template: its sidecar file if it has one,
+/// otherwise the bundled template's
+pub fn load(template_path: &str) -> Result<Calibration, String> {
+    let path = sidecar_path(template_path);
+    if !path.exists() {
+        return Ok(Calibration::BUNDLED);
+    }
+    let text = std::fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Calibration::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Profile value at a fractional position, by linear interpolation
+fn sample(profile: &[f32], pos: f32) -> f32 {
+    if pos < 0.0 || pos > (profile.len() - 1) as f32 {
+        return 0.0;
+    }
+    let idx = pos.floor() as usize;
+    let frac = pos - idx as f32;
+    let next = profile.get(idx + 1).copied().unwrap_or(0.0);
+    profile[idx] * (1.0 - frac) + next * frac
+}
+
+/// Smooth a profile with a box of the given half width, so a comb tooth
+/// landing anywhere on a glyph scores
+fn smooth(profile: &[f32], half_width: usize) -> Vec<f32> {
+    (0..profile.len())
+        .map(|idx| {
+            let range = idx.saturating_sub(half_width)..(idx + half_width + 1).min(profile.len());
+            profile[range].iter().sum::<f32>()
+        })
+        .collect()
+}
+
+/// Find the evenly spaced run of `teeth` peaks in a profile whose pitch
+/// lies in the given range: the pitch and the position of the first tooth
+fn fit_comb(profile: &[f32], teeth: usize, pitches: (f32, f32)) -> (f32, f32) {
+    let score = |pitch: f32, start: f32| (0..teeth).map(|tooth| sample(profile, start + tooth as f32 * pitch)).sum::<f32>();
+    let search = |pitches: (f32, f32), pitch_step: f32, starts: (f32, f32), start_step: f32| {
+        let mut best = (f32::MIN, pitches.0, starts.0);
+        let mut pitch = pitches.0;
+        while pitch <= pitches.1 {
+            let last_start = starts.1.min(profile.len() as f32 - 1.0 - (teeth - 1) as f32 * pitch);
+            let mut start = starts.0.max(0.0);
+            while start <= last_start {
+                let value = score(pitch, start);
+                if value > best.0 {
+                    best = (value, pitch, start);
+                }
+                start += start_step;
+            }
+            pitch += pitch_step;
+        }
+        (best.1, best.2)
+    };
+
+    // Coarse over the whole range, then fine around the best
+    let (pitch, start) = search(pitches, 0.05, (0.0, profile.len() as f32), 0.5);
+    search((pitch - 0.05, pitch + 0.05), 0.002, (start - 1.0, start + 1.0), 0.05)
+}
+
+/// Measure a template's punch grid from the digits printed in its rows 0
+/// to 9: the columns of digits give the column spacing, the rows of them the
+/// row spacing, and the punches are centred on them
+pub fn detect(template: &RgbImage) -> Result<Calibration, String> {
+    let (width, height) = template.dimensions();
+    let darkness: Vec<f32> = template
+        .pixels()
+        .map(|pixel| 255.0 - (0.299 * pixel.0[0] as f32 + 0.587 * pixel.0[1] as f32 + 0.114 * pixel.0[2] as f32))
+        .collect();
+
+    // Stock is the commonest shade; print is much darker than it
+    let mut sorted = darkness.clone();
+    sorted.sort_by(f32::total_cmp);
+    let stock = sorted[sorted.len() / 2];
+    let ink: Vec<f32> = darkness.iter().map(|&dark| if dark - stock > INK_CONTRAST { 1.0 } else { 0.0 }).collect();
+    if ink.iter().all(|&i| i == 0.0) {
+        return Err("no printing found on the template".to_string());
+    }
+    let ink_at = |x: u32, y: u32| ink[(y * width + x) as usize];
+
+    // Columns: 80 evenly spaced peaks of ink across the card
+    let column_profile: Vec<f32> = (0..width).map(|x| (0..height).map(|y| ink_at(x, y)).sum()).collect();
+    let widest = width as f32 / (COLUMNS - 1) as f32;
+    let column_pitches = (widest * 0.75, widest);
+    let column_profile = smooth(&column_profile, (column_pitches.0 / 4.0) as usize);
+    let (column_spacing, first_column) = fit_comb(&column_profile, COLUMNS, column_pitches);
+
+    // Rows: 10 evenly spaced bands of digits, counted within the columns
+    let left = first_column.max(0.0) as u32;
+    let right = ((first_column + (COLUMNS - 1) as f32 * column_spacing) as u32).min(width - 1);
+    let row_profile: Vec<f32> = (0..height).map(|y| (left..=right).map(|x| ink_at(x, y)).sum()).collect();
+    let tallest = height as f32 / (ROWS - 1) as f32;
+    let row_pitches = (tallest * 0.75, tallest);
+    let row_profile = smooth(&row_profile, (row_pitches.0 / 8.0) as usize);
+    let (row_spacing, first_digit_row) = fit_comb(&row_profile, DIGIT_ROWS, row_pitches);
+
+    let punch_width = column_spacing * HOLE_WIDTH_PER_COLUMN;
+    let punch_height = row_spacing * HOLE_HEIGHT_PER_ROW;
+    let calibration = Calibration {
+        first_punch_x: first_column - punch_width / 2.0,
+        first_punch_y: first_digit_row - FIRST_DIGIT_ROW as f32 * row_spacing - punch_height / 2.0,
+        column_spacing,
+        row_spacing,
+        punch_width,
+        punch_height,
+    };
+    if !calibration.fits(width, height) {
+        return Err(format!(
+            "the digit grid found (columns {:.1} px apart, rows {:.1} px apart) leaves no room for rows 12 and 11",
+            column_spacing, row_spacing
+        ));
+    }
+    Ok(calibration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_template_is_measured_as_shipped() {
+        let template = image::open("punchcard_template.png").unwrap().to_rgb8();
+        let measured = detect(&template).unwrap();
+        let shipped = load("punchcard_template.png").unwrap();
+        for (name, measured, shipped, bundled) in [
+            ("first_punch_x", measured.first_punch_x, shipped.first_punch_x, Calibration::BUNDLED.first_punch_x),
+            ("first_punch_y", measured.first_punch_y, shipped.first_punch_y, Calibration::BUNDLED.first_punch_y),
+            ("column_spacing", measured.column_spacing, shipped.column_spacing, Calibration::BUNDLED.column_spacing),
+            ("row_spacing", measured.row_spacing, shipped.row_spacing, Calibration::BUNDLED.row_spacing),
+            ("punch_width", measured.punch_width, shipped.punch_width, Calibration::BUNDLED.punch_width),
+            ("punch_height", measured.punch_height, shipped.punch_height, Calibration::BUNDLED.punch_height),
+        ] {
+            assert!((measured - shipped).abs() < 0.01, "{}: measured {} but shipped {}", name, measured, shipped);
+            assert!((measured - bundled).abs() < 0.01, "{}: measured {} but bundled {}", name, measured, bundled);
+        }
+        assert!(measured.fits(template.width(), template.height()));
+    }
+
+    #[test]
+    fn toml_round_trips() {
+        assert_eq!(Calibration::parse(&Calibration::BUNDLED.to_toml()).unwrap(), Calibration::BUNDLED);
+        assert!(Calibration::parse("first_punch_x = 1").is_err());
+        assert!(Calibration::parse("first_punch_x: 1").is_err());
+    }
+}
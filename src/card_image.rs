@@ -0,0 +1,303 @@
+// Card-image files: the deck as data an emulator's card reader can attach,
+// rather than as a picture of the cards.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+
+use crate::{ebcdic, PunchCard, COLUMNS, ROWS};
+
+/// Layout of a card-image file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CardImageFormat {
+    /// 80-byte ASCII records, one per card, no line ends
+    Ascii,
+    /// 80-byte EBCDIC (code page 037) records, one per card
+    Ebcdic,
+    /// SIMH column binary: two bytes per column, 160 bytes per card
+    Cbn,
+}
+
+impl CardImageFormat {
+    /// Guess the format from a file extension: `.cbn` for column binary,
+    /// `.ebc` or `.ebcdic` for EBCDIC, anything else ASCII
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("cbn") => CardImageFormat::Cbn,
+            Some("ebc") | Some("ebcdic") => CardImageFormat::Ebcdic,
+            _ => CardImageFormat::Ascii,
+        }
+    }
+}
+
+/// A card's text as an 80-byte ASCII record
+pub fn ascii_record(text: &str) -> Vec<u8> {
+    let mut record: Vec<u8> = text
+        .chars()
+        .take(COLUMNS)
+        .map(|ch| if ch.is_ascii() { ch as u8 } else { b' ' })
+        .collect();
+    record.resize(COLUMNS, b' ');
+    record
+}
+
+/// A card as an 80-byte EBCDIC record, each column's punches read through
+/// the EBCDIC card code, so multi-punched binary fields survive
+pub fn ebcdic_record(card: &PunchCard) -> Vec<u8> {
+    let mut record: Vec<u8> = card
+        .columns
+        .iter()
+        .map(|punches| ebcdic::from_card_code(column_bits(punches)).unwrap_or(ebcdic::SUBSTITUTE))
+        .collect();
+    record.resize(COLUMNS, ebcdic::SPACE);
+    record
+}
+
+/// The punches in one column as a 12-bit value, row 12 in the top bit down
+/// to row 9 in the bottom bit
+pub fn column_bits(punches: &[usize]) -> u16 {
+    punches
+        .iter()
+        .filter(|&&row| row < ROWS)
+        .fold(0, |bits, &row| bits | 1 << (ROWS - 1 - row))
+}
+
+/// A card in SIMH column-binary form. Each column takes two bytes: rows
+/// 12, 11, 0, 1, 2, 3 in the low six bits of the first, rows 4 to 9 in the
+/// second. The top bit of the card's first byte marks the start of a card.
+pub fn cbn_record(card: &PunchCard) -> Vec<u8> {
+    let mut record = Vec::with_capacity(COLUMNS * 2);
+    for punches in &card.columns {
+        let bits = column_bits(punches);
+        record.push((bits >> 6) as u8 & 0x3F);
+        record.push(bits as u8 & 0x3F);
+    }
+    record.resize(COLUMNS * 2, 0);
+    record[0] |= 0x80;
+    record
+}
+
+/// One record per card in the given format. ASCII takes the card text as
+/// read back from the punches; EBCDIC and column binary take the punches
+/// themselves.
+pub fn records(cards: &[PunchCard], images: &[String], format: CardImageFormat) -> Vec<Vec<u8>> {
+    match format {
+        CardImageFormat::Ascii => images.iter().map(|text| ascii_record(text)).collect(),
+        CardImageFormat::Ebcdic => cards.iter().map(ebcdic_record).collect(),
+        CardImageFormat::Cbn => cards.iter().map(cbn_record).collect(),
+    }
+}
+
+/// Write the deck as a card-image file
+pub fn write_deck<W: Write>(
+    writer: &mut W,
+    cards: &[PunchCard],
+    images: &[String],
+    format: CardImageFormat,
+) -> io::Result<()> {
+    for record in records(cards, images, format) {
+        writer.write_all(&record)?;
+    }
+    writer.flush()
+}
+
+/// Rows punched in a 12-bit column value
+pub fn column_punches(bits: u16) -> Vec<usize> {
+    (0..ROWS).filter(|row| bits & 1 << (ROWS - 1 - row) != 0).collect()
+}
+
+/// Punch a card-image record's characters. Characters the Hollerith table
+/// knows get its code; anything else gets its EBCDIC card code, as a card
+/// reader would have read it.
+fn punch_text_record(
+    chars: impl Iterator<Item = (char, u8)>,
+    encoding_map: &HashMap<char, Vec<usize>>,
+) -> PunchCard {
+    let mut card = PunchCard::new();
+    for (col_idx, (ch, ebcdic_byte)) in chars.take(COLUMNS).enumerate() {
+        let uppercase_ch = ch.to_uppercase().next().unwrap_or(ch);
+        card.columns[col_idx] = match encoding_map.get(&uppercase_ch) {
+            Some(punches) => punches.clone(),
+            None => column_punches(ebcdic::card_code(ebcdic_byte)),
+        };
+    }
+    card
+}
+
+/// Read a card-image file back into punch cards
+pub fn read_deck(
+    data: &[u8],
+    format: CardImageFormat,
+    encoding_map: &HashMap<char, Vec<usize>>,
+) -> Result<Vec<PunchCard>, String> {
+    match format {
+        CardImageFormat::Cbn => {
+            if !data.len().is_multiple_of(COLUMNS * 2) {
+                return Err(format!("{} bytes is not a whole number of 160-byte column-binary cards", data.len()));
+            }
+            data.chunks(COLUMNS * 2)
+                .enumerate()
+                .map(|(idx, record)| {
+                    if record[0] & 0x80 == 0 {
+                        return Err(format!("card {} does not start with a start-of-card mark", idx + 1));
+                    }
+                    let mut card = PunchCard::new();
+                    for (col_idx, pair) in record.chunks(2).enumerate() {
+                        let bits = (pair[0] as u16 & 0x3F) << 6 | pair[1] as u16 & 0x3F;
+                        card.columns[col_idx] = column_punches(bits);
+                    }
+                    Ok(card)
+                })
+                .collect()
+        }
+        CardImageFormat::Ebcdic => Ok(data
+            .chunks(COLUMNS)
+            .map(|record| {
+                let chars = record.iter().map(|&byte| (ebcdic::to_char(byte).unwrap_or('\0'), byte));
+                punch_text_record(chars, encoding_map)
+            })
+            .collect()),
+        CardImageFormat::Ascii => {
+            // Accept one card per line as well as fixed 80-byte records
+            let records: Vec<&[u8]> = if data.contains(&b'\n') {
+                data.strip_suffix(b"\n")
+                    .unwrap_or(data)
+                    .split(|&byte| byte == b'\n')
+                    .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                    .collect()
+            } else {
+                data.chunks(COLUMNS).collect()
+            };
+            Ok(records
+                .into_iter()
+                .map(|record| {
+                    let chars = record.iter().map(|&byte| {
+                        if byte.is_ascii() {
+                            (byte as char, ebcdic::from_char(byte as char))
+                        } else {
+                            ('\0', ebcdic::SUBSTITUTE)
+                        }
+                    });
+                    punch_text_record(chars, encoding_map)
+                })
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_hollerith_encoding;
+
+    #[test]
+    fn text_records_are_80_bytes() {
+        let ascii = ascii_record("HELLO é");
+        assert_eq!(ascii.len(), COLUMNS);
+        assert_eq!(&ascii[..7], b"HELLO  ");
+        assert!(ascii[7..].iter().all(|&byte| byte == b' '));
+        assert_eq!(ascii_record(&"X".repeat(90)).len(), COLUMNS);
+
+        let ebcdic = ebcdic_record(&PunchCard::from_control_line("A1&", &get_hollerith_encoding()));
+        assert_eq!(ebcdic.len(), COLUMNS);
+        assert_eq!(&ebcdic[..3], [0xC1, 0xF1, 0x50]);
+        assert!(ebcdic[3..].iter().all(|&byte| byte == ebcdic::SPACE));
+    }
+
+    #[test]
+    fn cbn_packs_each_column_into_two_bytes() {
+        let mut card = PunchCard::new();
+        card.columns[0] = vec![0];
+        card.columns[1] = vec![11];
+        card.columns[2] = (0..ROWS).collect();
+        let record = cbn_record(&card);
+        assert_eq!(record.len(), COLUMNS * 2);
+        // Row 12 is the top bit of the first six, with the start-of-card mark
+        assert_eq!(record[..6], [0x80 | 0x20, 0x00, 0x00, 0x01, 0x3F, 0x3F]);
+        assert!(record[6..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn write_deck_writes_one_record_per_card() {
+        let encoding_map = get_hollerith_encoding();
+        let images = ["//JOB1", "/*"].map(str::to_string);
+        let cards: Vec<PunchCard> = images.iter().map(|line| PunchCard::from_control_line(line, &encoding_map)).collect();
+        for (format, size) in [(CardImageFormat::Ascii, COLUMNS), (CardImageFormat::Ebcdic, COLUMNS), (CardImageFormat::Cbn, COLUMNS * 2)] {
+            let mut data = Vec::new();
+            write_deck(&mut data, &cards, &images, format).unwrap();
+            assert_eq!(data.len(), 2 * size, "{:?}", format);
+        }
+        let mut data = Vec::new();
+        write_deck(&mut data, &cards, &images, CardImageFormat::Cbn).unwrap();
+        assert!(data[COLUMNS * 2] & 0x80 != 0);
+        assert_eq!(CardImageFormat::from_path("deck.CBN"), CardImageFormat::Cbn);
+        assert_eq!(CardImageFormat::from_path("deck.ebc"), CardImageFormat::Ebcdic);
+        assert_eq!(CardImageFormat::from_path("deck.txt"), CardImageFormat::Ascii);
+    }
+
+    const TEXT: [&str; 2] = ["//HELLO    JOB 1-2/3 &SYSUID", "       01  WS-COUNT  PIC 9(4)"];
+
+    fn round_trip(cards: &[PunchCard], format: CardImageFormat) -> Vec<PunchCard> {
+        let encoding_map = get_hollerith_encoding();
+        let images: Vec<String> = cards.iter().map(|card| card.decode(&crate::get_hollerith_decoding())).collect();
+        let mut data = Vec::new();
+        write_deck(&mut data, cards, &images, format).unwrap();
+        read_deck(&data, format, &encoding_map).unwrap()
+    }
+
+    fn text_cards() -> Vec<PunchCard> {
+        let encoding_map = get_hollerith_encoding();
+        TEXT.iter().map(|line| PunchCard::from_control_line(line, &encoding_map)).collect()
+    }
+
+    fn columns(cards: &[PunchCard]) -> Vec<Vec<Vec<usize>>> {
+        cards.iter().map(|card| card.columns.clone()).collect()
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let cards = text_cards();
+        assert_eq!(columns(&round_trip(&cards, CardImageFormat::Ascii)), columns(&cards));
+    }
+
+    #[test]
+    fn ebcdic_round_trip_keeps_multi_punches() {
+        let mut cards = text_cards();
+        // 12-0-9-8-1 is EBCDIC X'80', which no character in the table has
+        cards[1].columns[79] = vec![0, 2, 3, 10, 11];
+        assert_eq!(columns(&round_trip(&cards, CardImageFormat::Ebcdic)), columns(&cards));
+    }
+
+    #[test]
+    fn cbn_round_trip_keeps_any_punches() {
+        let mut card = PunchCard::new();
+        for (col_idx, punches) in card.columns.iter_mut().enumerate() {
+            *punches = column_punches((col_idx as u32 * 4093 % 4096) as u16);
+        }
+        let cards = vec![card, PunchCard::new()];
+        assert_eq!(columns(&round_trip(&cards, CardImageFormat::Cbn)), columns(&cards));
+    }
+
+    #[test]
+    fn ascii_reads_lines_or_fixed_records() {
+        let encoding_map = get_hollerith_encoding();
+        let lines = read_deck(b"A\r\nB\n", CardImageFormat::Ascii, &encoding_map).unwrap();
+        let fixed = read_deck(format!("{:<80}{:<80}", "A", "B").as_bytes(), CardImageFormat::Ascii, &encoding_map).unwrap();
+        assert_eq!(columns(&lines), columns(&fixed));
+        assert_eq!(lines[1].columns[0], vec![0, 4]);
+    }
+
+    #[test]
+    fn cbn_rejects_cards_without_a_start_mark() {
+        let Err(err) = read_deck(&[0u8; COLUMNS * 2], CardImageFormat::Cbn, &get_hollerith_encoding()) else {
+            panic!("read a card without a start-of-card mark");
+        };
+        assert!(err.contains("start-of-card"), "{}", err);
+    }
+}
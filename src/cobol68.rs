@@ -0,0 +1,143 @@
+// Checks for source the ANS COBOL compiler on MVS 3.8j (IKFCBL00) cannot
+// compile. It implements the 1968 standard plus IBM extensions, so anything
+// that came with COBOL-74, COBOL-85 or Enterprise COBOL is flagged.
+
+/// Reserved words that did not exist before COBOL-85
+const LATER_WORDS: &[(&str, &str)] = &[
+    ("EVALUATE", "EVALUATE statement"),
+    ("INITIALIZE", "INITIALIZE statement"),
+    ("CONTINUE", "CONTINUE statement"),
+    ("FUNCTION", "intrinsic functions"),
+    ("TRUE", "TRUE and FALSE"),
+    ("FALSE", "TRUE and FALSE"),
+    ("CONTENT", "CALL ... BY CONTENT"),
+    ("VALUE", "CALL ... BY VALUE"),
+    ("GLOBAL", "GLOBAL data items"),
+    ("EXTERNAL", "EXTERNAL data items"),
+    ("LOCAL-STORAGE", "LOCAL-STORAGE SECTION"),
+    ("REPLACE", "REPLACE statement"),
+];
+
+/// Explicit scope terminators arrived with COBOL-85. END-OF-PAGE is the
+/// only older word with the same prefix.
+fn is_scope_terminator(word: &str) -> bool {
+    word.starts_with("END-") && word != "END-OF-PAGE"
+}
+
+/// Blank out literals so words inside them are not mistaken for syntax
+fn without_literals(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_quote = None;
+    for ch in text.chars() {
+        match (ch, in_quote) {
+            ('\'' | '"', None) => in_quote = Some(ch),
+            (c, Some(q)) if c == q => in_quote = None,
+            (_, Some(_)) => {
+                result.push(' ');
+                continue;
+            }
+            _ => {}
+        }
+        result.push(ch);
+    }
+    result
+}
+
+/// Whether a card is a CBL/PROCESS statement or a `>>` compiler directive.
+/// IKFCBL00 rejects both, so they are left out of MVS 3.8j decks; the
+/// options on CBL/PROCESS go into the compile step's PARM= instead.
+pub fn is_directive(line: &str) -> bool {
+    if matches!(line.chars().nth(6), Some('*') | Some('/')) {
+        return false;
+    }
+    let upper = line.to_uppercase();
+    let text = upper.trim();
+    text.starts_with("CBL ") || text.starts_with("PROCESS ") || text == "CBL" || text == "PROCESS" || text.starts_with(">>")
+}
+
+/// Find the constructs the MVS 3.8j compiler rejects. Each construct is
+/// reported once, at its first line, with how often it occurs.
+pub fn check(cobol_lines: &[String]) -> Vec<String> {
+    // (construct, first line, occurrences), in order of first appearance
+    let mut found: Vec<(String, usize, usize)> = Vec::new();
+    let mut note = |construct: String, line_num: usize| {
+        match found.iter_mut().find(|(c, _, _)| *c == construct) {
+            Some(entry) => entry.2 += 1,
+            None => found.push((construct, line_num, 1)),
+        }
+    };
+
+    let mut value_words_allowed = true;
+    for (idx, line) in cobol_lines.iter().enumerate() {
+        let line_num = idx + 1;
+        if matches!(line.chars().nth(6), Some('*') | Some('/')) {
+            continue;
+        }
+
+        // Directive cards are taken out of the deck, not reported
+        if is_directive(line) {
+            continue;
+        }
+        let upper = without_literals(&line.to_uppercase());
+        if upper.contains("*>") {
+            note("*> inline comments".to_string(), line_num);
+        }
+        if upper.contains("EXEC SQL") || upper.contains("EXEC CICS") {
+            note("EXEC SQL / EXEC CICS (no DB2 or CICS on MVS 3.8j)".to_string(), line_num);
+        }
+
+        // VALUE is only suspicious as CALL ... BY VALUE, not in the DATA DIVISION
+        if upper.contains("PROCEDURE DIVISION") {
+            value_words_allowed = false;
+        }
+
+        for word in upper.split(|c: char| c.is_whitespace() || c == '.' || c == ',') {
+            if word.is_empty() {
+                continue;
+            }
+            if is_scope_terminator(word) {
+                note(format!("{} scope terminator", word), line_num);
+            } else if let Some((_, construct)) = LATER_WORDS.iter().find(|(w, _)| *w == word) {
+                if word == "VALUE" && value_words_allowed {
+                    continue;
+                }
+                note(construct.to_string(), line_num);
+            } else if word.contains(':') && word.contains('(') {
+                note("reference modification".to_string(), line_num);
+            }
+        }
+
+        if upper.contains("END PROGRAM") {
+            note("END PROGRAM header".to_string(), line_num);
+        }
+        if upper.contains("NOT AT END") || upper.contains("NOT ON ") || upper.contains("NOT INVALID") {
+            note("NOT AT END / NOT ON / NOT INVALID KEY phrases".to_string(), line_num);
+        }
+    }
+
+    found
+        .into_iter()
+        .map(|(construct, line_num, count)| {
+            if count == 1 {
+                format!("Line {}: {} not supported by COBOL-68", line_num, construct)
+            } else {
+                format!("Line {}: {} not supported by COBOL-68 ({} occurrences)", line_num, construct, count)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directives_are_recognised() {
+        assert!(is_directive("       CBL APOST,NOSEQ"));
+        assert!(is_directive("       PROCESS"));
+        assert!(is_directive("       >>SOURCE FORMAT IS FIXED"));
+        assert!(!is_directive("      * CBL IN A COMMENT"));
+        assert!(!is_directive("           MOVE CBL-COUNT TO X."));
+        assert!(check(&["       CBL APOST".to_string()]).is_empty());
+    }
+}
@@ -0,0 +1,325 @@
+/// An Enterprise COBOL compiler option: its full name, the abbreviations the
+/// compiler accepts, and whether it has a NO form
+struct OptionSpec {
+    name: &'static str,
+    abbreviations: &'static [&'static str],
+    negatable: bool,
+}
+
+const fn opt(name: &'static str, abbreviations: &'static [&'static str], negatable: bool) -> OptionSpec {
+    OptionSpec { name, abbreviations, negatable }
+}
+
+// Enterprise COBOL for z/OS 6.x compiler options
+const KNOWN_OPTIONS: &[OptionSpec] = &[
+    opt("ADATA", &[], true),
+    opt("ADV", &[], true),
+    opt("AFP", &[], false),
+    opt("APOST", &[], false),
+    opt("ARCH", &[], false),
+    opt("ARITH", &["AR"], false),
+    opt("AWO", &[], true),
+    opt("BLOCK0", &[], true),
+    opt("BUFSIZE", &["BUF"], false),
+    opt("CICS", &[], true),
+    opt("CODEPAGE", &["CP"], false),
+    opt("COMPILE", &["C"], true),
+    opt("COPYLOC", &[], true),
+    opt("COPYRIGHT", &[], true),
+    opt("CURRENCY", &["CURR"], true),
+    opt("DATA", &[], false),
+    opt("DBCS", &[], true),
+    opt("DECK", &["D"], true),
+    opt("DEFINE", &["DEF"], true),
+    opt("DIAGTRUNC", &["DTR"], true),
+    opt("DISPSIGN", &["DS"], false),
+    opt("DLL", &[], true),
+    opt("DUMP", &["DU"], true),
+    opt("DYNAM", &["DYN"], true),
+    opt("EXIT", &["EX"], true),
+    opt("EXPORTALL", &["EXP"], true),
+    opt("FASTSRT", &["FSRT"], true),
+    opt("FLAG", &["F"], true),
+    opt("FLAGSTD", &[], true),
+    opt("HGPR", &[], false),
+    opt("INITCHECK", &["IC"], true),
+    opt("INITIAL", &[], true),
+    opt("INLINE", &[], true),
+    opt("INTDATE", &[], false),
+    opt("INVDATA", &[], true),
+    opt("LANGUAGE", &["LANG"], false),
+    opt("LINECOUNT", &["LC"], false),
+    opt("LIST", &[], true),
+    opt("LP", &[], false),
+    opt("MAP", &[], true),
+    opt("MAXPCF", &[], false),
+    opt("MDECK", &["MD"], true),
+    opt("NAME", &[], true),
+    opt("NSYMBOL", &["NS"], false),
+    opt("NUMBER", &["NUM"], true),
+    opt("NUMCHECK", &["NC"], true),
+    opt("NUMPROC", &[], false),
+    opt("OBJECT", &["OBJ"], true),
+    opt("OFFSET", &["OFF"], true),
+    opt("OPTFILE", &[], false),
+    opt("OPTIMIZE", &["OPT"], true),
+    opt("OUTDD", &["OUT"], false),
+    opt("PARMCHECK", &["PC"], true),
+    opt("PGMNAME", &["PGMN"], false),
+    opt("QUALIFY", &[], false),
+    opt("QUOTE", &["Q"], false),
+    opt("RENT", &[], true),
+    opt("RMODE", &[], false),
+    opt("RULES", &[], false),
+    opt("SEQUENCE", &["SEQ"], true),
+    opt("SERVICE", &["SERV"], true),
+    opt("SOURCE", &["S"], true),
+    opt("SQL", &[], true),
+    opt("SQLCCSID", &["SQLC"], true),
+    opt("SQLIMS", &[], true),
+    opt("SSRANGE", &["SSR"], true),
+    opt("STGOPT", &["SO"], true),
+    opt("SUPPRESS", &[], true),
+    opt("TERMINAL", &["TERM"], true),
+    opt("TEST", &[], true),
+    opt("THREAD", &[], true),
+    opt("TRUNC", &[], false),
+    opt("TUNE", &[], false),
+    opt("VBREF", &[], true),
+    opt("VLR", &[], false),
+    opt("VSAMOPENFS", &[], false),
+    opt("WORD", &["WD"], true),
+    opt("XMLPARSE", &["XP"], false),
+    opt("XREF", &["X"], true),
+    opt("ZONECHECK", &[], true),
+    opt("ZONEDATA", &["ZD"], false),
+    opt("ZWB", &[], true),
+];
+
+// Compiler directives (>>name) recognised by Enterprise COBOL
+const KNOWN_DIRECTIVES: &[&str] = &[
+    "CALLINTERFACE", "CALLINT", "DEFINE", "EVALUATE", "WHEN", "END-EVALUATE",
+    "IF", "ELSE", "END-IF", "INLINE", "NOINLINE", "TURN",
+];
+
+/// A compiler option as written, resolved to its full name
+#[derive(Clone, Debug)]
+pub struct CompilerOption {
+    /// Option as it appeared, e.g. `NOOPT` or `TRUNC(BIN)`
+    pub text: String,
+    /// Full option name without the NO prefix, e.g. `OPTIMIZE`
+    pub name: &'static str,
+    pub negated: bool,
+    /// Suboptions inside the parentheses, if any
+    pub suboptions: Option<String>,
+}
+
+impl CompilerOption {
+    /// Look an option up in the table of known options
+    pub fn parse(text: &str) -> Option<Self> {
+        let upper = text.trim().to_uppercase();
+        let (keyword, suboptions) = match upper.find('(') {
+            Some(pos) => (&upper[..pos], Some(upper[pos + 1..].trim_end_matches(')').to_string())),
+            None => (upper.as_str(), None),
+        };
+
+        let find = |word: &str| {
+            KNOWN_OPTIONS
+                .iter()
+                .find(|spec| spec.name == word || spec.abbreviations.contains(&word))
+        };
+
+        let (spec, negated) = match find(keyword) {
+            Some(spec) => (spec, false),
+            None => {
+                let positive = keyword.strip_prefix("NO")?;
+                let spec = find(positive).filter(|spec| spec.negatable)?;
+                (spec, true)
+            }
+        };
+
+        Some(CompilerOption {
+            text: upper.clone(),
+            name: spec.name,
+            negated,
+            suboptions,
+        })
+    }
+
+    /// Options that override each other. QUOTE and APOST are two settings
+    /// of the same thing.
+    fn family(&self) -> &'static str {
+        match self.name {
+            "APOST" => "QUOTE",
+            name => name,
+        }
+    }
+
+    /// Options are equivalent if they select the same setting, however
+    /// they were abbreviated
+    fn same_setting(&self, other: &CompilerOption) -> bool {
+        self.name == other.name && self.negated == other.negated && self.suboptions == other.suboptions
+    }
+}
+
+/// Compiler options for the compile step, merged from the profile and the
+/// source deck
+pub struct ResolvedOptions {
+    /// Options for the compile step's PARM=, in order
+    pub parm: Vec<String>,
+    /// Problems found in CBL/PROCESS statements and >> directives
+    pub warnings: Vec<String>,
+}
+
+/// Split an option list on commas and blanks, leaving separators inside
+/// parentheses or quotes alone
+fn split_options(text: &str) -> Vec<String> {
+    let mut options = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_quote = None;
+
+    for ch in text.chars() {
+        match ch {
+            '\'' | '"' if in_quote == Some(ch) => in_quote = None,
+            '\'' | '"' if in_quote.is_none() => in_quote = Some(ch),
+            '(' if in_quote.is_none() => depth += 1,
+            ')' if in_quote.is_none() => depth -= 1,
+            ',' | ' ' if in_quote.is_none() && depth == 0 => {
+                if !current.is_empty() {
+                    options.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+
+    if !current.is_empty() {
+        options.push(current);
+    }
+    options
+}
+
+/// The part of a source line the compiler reads: without a sequence number
+/// in columns 1-6 and without the identification area in columns 73-80
+fn program_text(line: &str) -> &str {
+    let line = line.get(..72).unwrap_or(line);
+    let is_sequence = line.len() > 6 && line.get(..6).is_some_and(|seq| seq.chars().all(|c| c.is_ascii_digit()));
+    if is_sequence { line[6..].trim() } else { line.trim() }
+}
+
+/// Collect the CBL/PROCESS options and >> directives in the source,
+/// checking each against the options Enterprise COBOL knows
+fn scan_source(cobol_lines: &[String], warnings: &mut Vec<String>) -> Vec<CompilerOption> {
+    let mut options = Vec::new();
+
+    for (line_num, line) in cobol_lines.iter().enumerate() {
+        let text = program_text(line);
+        let upper = text.to_uppercase();
+        let mut words = upper.splitn(2, ' ');
+        let first = words.next().unwrap_or("");
+
+        if first == "CBL" || first == "PROCESS" {
+            for option_text in split_options(words.next().unwrap_or("")) {
+                match CompilerOption::parse(&option_text) {
+                    Some(option) => options.push(option),
+                    None => warnings.push(format!(
+                        "Line {}: unknown compiler option {} ignored",
+                        line_num + 1,
+                        option_text
+                    )),
+                }
+            }
+        } else if let Some(directive) = upper.strip_prefix(">>") {
+            let name = directive.split_whitespace().next().unwrap_or("");
+            if !KNOWN_DIRECTIVES.contains(&name) {
+                warnings.push(format!(
+                    "Line {}: compiler directive >>{} is not supported by Enterprise COBOL",
+                    line_num + 1,
+                    name
+                ));
+            }
+        }
+    }
+
+    options
+}
+
+/// Merge the options requested in the source deck into the profile's
+/// compile options. Source options win, as they would in the compiler, but
+/// every profile option they override is reported.
+pub fn resolve(profile_options: &[String], cobol_lines: &[String]) -> ResolvedOptions {
+    let mut warnings = Vec::new();
+    let source_options = scan_source(cobol_lines, &mut warnings);
+
+    // Each option remembers whether it came from the profile
+    let mut merged: Vec<(CompilerOption, bool)> = Vec::new();
+    for text in profile_options {
+        match CompilerOption::parse(text) {
+            Some(option) => merged.push((option, true)),
+            None => warnings.push(format!("Profile option {} is not a known compiler option", text)),
+        }
+    }
+
+    for option in source_options {
+        if let Some(pos) = merged.iter().position(|(o, _)| o.family() == option.family()) {
+            let (existing, from_profile) = merged.remove(pos);
+            if from_profile && !existing.same_setting(&option) {
+                warnings.push(format!(
+                    "Source option {} conflicts with profile option {}; using {}",
+                    option.text, existing.text, option.text
+                ));
+            }
+        }
+        merged.push((option, false));
+    }
+
+    ResolvedOptions {
+        parm: merged.into_iter().map(|(option, _)| option.text).collect(),
+        warnings,
+    }
+}
+
+/// Translate Enterprise COBOL options into their nearest ANS COBOL
+/// (IKFCBL00) equivalents for MVS 3.8j. Returns the translated options and
+/// the ones the old compiler has no counterpart for.
+pub fn to_ans_cobol(options: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut translated = Vec::new();
+    let mut dropped = Vec::new();
+
+    for text in options {
+        let Some(option) = CompilerOption::parse(text) else {
+            dropped.push(text.clone());
+            continue;
+        };
+        let no = if option.negated { "NO" } else { "" };
+        let sub = option.suboptions.as_deref().unwrap_or("");
+
+        let ans = match option.name {
+            "SOURCE" | "XREF" | "DECK" | "ZWB" => Some(format!("{}{}", no, option.name)),
+            "MAP" => Some(format!("{}DMAP", no)),
+            "LIST" => Some(format!("{}PMAP", no)),
+            "OFFSET" => Some(format!("{}CLIST", no)),
+            "SEQUENCE" => Some(format!("{}SEQ", no)),
+            "OBJECT" => Some(format!("{}LOAD", no)),
+            "QUOTE" | "APOST" => Some(option.name.to_string()),
+            "TRUNC" if sub.starts_with("STD") => Some("TRUNC".to_string()),
+            "TRUNC" => Some("NOTRUNC".to_string()),
+            "FLAG" if option.negated => None,
+            "FLAG" if sub.starts_with('E') || sub.starts_with('S') || sub.starts_with('U') => Some("FLAGE".to_string()),
+            "FLAG" => Some("FLAGW".to_string()),
+            "LINECOUNT" if !sub.is_empty() => Some(format!("LINECNT={}", sub)),
+            _ => None,
+        };
+
+        match ans {
+            Some(ans) if !translated.contains(&ans) => translated.push(ans),
+            Some(_) => {}
+            None => dropped.push(option.text),
+        }
+    }
+
+    (translated, dropped)
+}
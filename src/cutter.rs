@@ -0,0 +1,366 @@
+// Cutting real cards: each card's outline and its punches as closed paths
+// at true size, in millimetres, for a laser cutter or cutting plotter,
+// written as SVG or DXF. The cards are set on the cutter's sheet as they
+// would be on a printed page, and the punches use the same grid as the
+// PDF, so the cut cards match the printed ones.
+//
+// A beam or blade takes away a kerf of material along each path. Kerf
+// compensation moves every path by half the kerf so what is left is the
+// drawn size: the card's outline outwards, the holes inwards. The holes
+// come first in each card's paths, so the card is not cut free of the
+// sheet before its holes are cut.
+//
+// A print shop's cutting table takes its paths from the PDF instead: lines
+// stroked in a spot colour named `CutContour`, which the printer leaves
+// unprinted. Those paths come from here too, so both cutters cut the same
+// holes.
+
+use std::path::Path;
+
+use clap::ValueEnum;
+use lopdf::{Dictionary, Object};
+
+use crate::calibration::Calibration;
+use crate::layout::Layout;
+use crate::{vector_card, PunchCard, CARD_HEIGHT_MM, CARD_WIDTH_MM, PT_PER_MM};
+
+/// Line segments to each quarter circle of a rounded corner
+const ARC_SEGMENTS: usize = 9;
+
+/// Name of the spot colour cutting tables follow, used as its resource name
+/// too
+pub const CUT_CONTOUR: &str = "CutContour";
+
+/// DXF layers, so the holes and outlines can be given their own settings
+const HOLE_LAYER: &str = "PUNCHES";
+const OUTLINE_LAYER: &str = "OUTLINE";
+
+/// Cutting file format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CutFormat {
+    /// SVG drawn in hairline red strokes
+    Svg,
+    /// AutoCAD R12 DXF polylines
+    Dxf,
+}
+
+impl CutFormat {
+    /// Guess the format from a file extension: `.dxf` for DXF, anything
+    /// else SVG
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("dxf") => CutFormat::Dxf,
+            _ => CutFormat::Svg,
+        }
+    }
+}
+
+/// How the cards are cut
+#[derive(Clone, Debug)]
+pub struct CutOptions {
+    /// Radius of the card's corners, all but the one cut off; 0 for square
+    pub corner_radius_mm: f32,
+    /// Width of material the cutter takes away
+    pub kerf_mm: f32,
+}
+
+/// A closed path in millimetres, up from the bottom of the sheet
+pub struct CutPath {
+    pub outline: bool,
+    pub points: Vec<(f32, f32)>,
+}
+
+/// Points along an arc about a centre, anticlockwise between two angles in
+/// degrees
+fn arc(centre: (f32, f32), radius: f32, from_deg: f32, to_deg: f32) -> impl Iterator<Item = (f32, f32)> {
+    (0..=ARC_SEGMENTS).map(move |step| {
+        let angle = (from_deg + (to_deg - from_deg) * step as f32 / ARC_SEGMENTS as f32).to_radians();
+        (centre.0 + radius * angle.cos(), centre.1 + radius * angle.sin())
+    })
+}
+
+/// The card's outline in millimetres from its bottom-left corner, moved out
+/// by `offset`: rounded corners, and the corner cut at top left
+pub fn card_outline(radius: f32, offset: f32) -> Result<Vec<(f32, f32)>, String> {
+    if !radius.is_finite() || !(0.0..=CARD_HEIGHT_MM / 4.0).contains(&radius) {
+        return Err(format!("corner radius of {} mm must be from 0 to {:.1} mm", radius, CARD_HEIGHT_MM / 4.0));
+    }
+    let (width, height) = (CARD_WIDTH_MM, CARD_HEIGHT_MM);
+    let mut points = Vec::new();
+    let mut corner = |corner: (f32, f32), centre: (f32, f32), from_deg: f32| match radius > 0.0 {
+        true => points.extend(arc(centre, radius + offset, from_deg, from_deg + 90.0)),
+        false => points.push(corner),
+    };
+    corner((-offset, -offset), (radius, radius), 180.0);
+    corner((width + offset, -offset), (width - radius, radius), 270.0);
+    corner((width + offset, height + offset), (width - radius, height - radius), 0.0);
+
+    // The corner cut runs from the top edge down to the left edge; moving
+    // it out along its normal keeps it parallel to the cut on the card
+    let (cut_x, cut_y) = (
+        vector_card::CORNER_CUT_PX.0 / vector_card::WIDTH_PX * width,
+        vector_card::CORNER_CUT_PX.1 / vector_card::HEIGHT_PX * height,
+    );
+    let length = cut_x.hypot(cut_y);
+    let normal = (-cut_y / length, cut_x / length);
+    let along = normal.1 * (height - cut_y) + offset;
+    points.push(((along - normal.1 * (height + offset)) / normal.0, height + offset));
+    points.push((-offset, (along + normal.0 * offset) / normal.1));
+    Ok(points)
+}
+
+/// A punch's rectangle in millimetres from the card's bottom-left corner:
+/// x, y, width, height. The punches are placed as in the PDF, through the
+/// template's grid on an image of the given size stretched over the card.
+pub fn punch_rect(grid: &Calibration, image_size: (f32, f32), col_idx: usize, row: usize) -> (f32, f32, f32, f32) {
+    let scale = (CARD_WIDTH_MM / image_size.0, CARD_HEIGHT_MM / image_size.1);
+    grid.punch_rect(col_idx, row, image_size.1, scale)
+}
+
+/// Every path to cut the cards at the given positions on a sheet
+pub fn sheet_paths(
+    layout: &Layout,
+    cards: &[&PunchCard],
+    grid: &Calibration,
+    image_size: (f32, f32),
+    options: &CutOptions,
+) -> Result<Vec<CutPath>, String> {
+    let offset = options.kerf_mm / 2.0;
+    let punch_width = grid.punch_width * CARD_WIDTH_MM / image_size.0;
+    if !options.kerf_mm.is_finite() || options.kerf_mm < 0.0 || options.kerf_mm >= punch_width {
+        return Err(format!(
+            "kerf of {} mm must be 0 or more and narrower than a {:.2} mm punch",
+            options.kerf_mm, punch_width
+        ));
+    }
+
+    let outline = card_outline(options.corner_radius_mm, offset)?;
+    let mut paths = Vec::new();
+    for (position, card) in cards.iter().enumerate() {
+        // Cards are placed on the sheet as on a page, which is in points
+        let [a, b, c, d, e, f] = layout.card_transform(position);
+        let place = |(x, y): (f32, f32)| (a * x + c * y + e / PT_PER_MM, b * x + d * y + f / PT_PER_MM);
+
+        for (col_idx, punches) in card.columns.iter().enumerate() {
+            for &row in punches {
+                let (x, y, width, height) = punch_rect(grid, image_size, col_idx, row);
+                let (left, bottom, right, top) = (x + offset, y + offset, x + width - offset, y + height - offset);
+                let corners = [(left, bottom), (right, bottom), (right, top), (left, top)];
+                paths.push(CutPath { outline: false, points: corners.into_iter().map(place).collect() });
+            }
+        }
+        paths.push(CutPath { outline: true, points: outline.iter().copied().map(place).collect() });
+    }
+    Ok(paths)
+}
+
+/// The `CutContour` colour space: a spot colour shown as magenta where the
+/// separation is not available
+pub fn cut_contour_colour_space() -> Object {
+    let mut magenta = Dictionary::new();
+    magenta.set("FunctionType", 2);
+    magenta.set("Domain", vec![0.into(), 1.into()]);
+    magenta.set("C0", vec![0.into(), 0.into(), 0.into(), 0.into()]);
+    magenta.set("C1", vec![0.into(), 1.into(), 0.into(), 0.into()]);
+    magenta.set("N", 1);
+    Object::Array(vec![
+        Object::Name(b"Separation".to_vec()),
+        Object::Name(CUT_CONTOUR.as_bytes().to_vec()),
+        Object::Name(b"DeviceCMYK".to_vec()),
+        Object::Dictionary(magenta),
+    ])
+}
+
+/// A sheet's paths as an SVG the sheet's size, in millimetres
+pub fn svg(paths: &[CutPath], sheet_width_mm: f32, sheet_height_mm: f32) -> String {
+    let mut svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.3}mm\" height=\"{h:.3}mm\" viewBox=\"0 0 {w:.3} {h:.3}\">\n\
+         <g fill=\"none\" stroke=\"#ff0000\" stroke-width=\"0.01\">\n",
+        w = sheet_width_mm,
+        h = sheet_height_mm
+    );
+    for path in paths {
+        // SVG runs top down
+        let points: Vec<String> = path.points.iter().map(|&(x, y)| format!("{:.3} {:.3}", x, sheet_height_mm - y)).collect();
+        svg.push_str(&format!("<path d=\"M {} Z\"/>\n", points.join(" L ")));
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// A sheet's paths as an ASCII DXF in millimetres. R12 has no setting for
+/// drawing units, so the cutter has to be told they are millimetres.
+pub fn dxf(paths: &[CutPath]) -> String {
+    let mut dxf = String::new();
+    let mut group = |code: u32, value: &str| dxf.push_str(&format!("{}\n{}\n", code, value));
+    group(0, "SECTION");
+    group(2, "HEADER");
+    group(9, "$ACADVER");
+    group(1, "AC1009");
+    group(0, "ENDSEC");
+    group(0, "SECTION");
+    group(2, "ENTITIES");
+    for path in paths {
+        let layer = if path.outline { OUTLINE_LAYER } else { HOLE_LAYER };
+        // A closed polyline with its vertices following
+        group(0, "POLYLINE");
+        group(8, layer);
+        group(66, "1");
+        group(10, "0.0");
+        group(20, "0.0");
+        group(30, "0.0");
+        group(70, "1");
+        for &(x, y) in &path.points {
+            group(0, "VERTEX");
+            group(8, layer);
+            group(10, &format!("{:.4}", x));
+            group(20, &format!("{:.4}", y));
+            group(30, "0.0");
+        }
+        group(0, "SEQEND");
+        group(8, layer);
+    }
+    group(0, "ENDSEC");
+    group(0, "EOF");
+    dxf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::content::Content;
+    use lopdf::Document;
+
+    use crate::layout::PageSize;
+    use crate::printer::PrinterProfile;
+    use crate::{generate_punch_card_pdf, get_hollerith_encoding, Deck, PdfOptions, COLUMNS};
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.001, "{} mm, expected {} mm", actual, expected);
+    }
+
+    /// The drawn card's size in template pixels
+    const IMAGE_SIZE: (f32, f32) = (vector_card::WIDTH_PX, vector_card::HEIGHT_PX);
+
+    #[test]
+    fn punches_follow_the_template_grid() {
+        // Columns 1 and 80 in row 12, on the bundled grid stretched over
+        // the card's 187.325 mm
+        let scale = CARD_WIDTH_MM / IMAGE_SIZE.0;
+        let (x, _, width, _) = punch_rect(&Calibration::BUNDLED, IMAGE_SIZE, 0, 0);
+        assert_near(x + width / 2.0, 7.3642);
+        let (x, y, width, height) = punch_rect(&Calibration::BUNDLED, IMAGE_SIZE, COLUMNS - 1, 0);
+        assert_near(x + width / 2.0, 179.8983);
+        assert_near(width, 5.9 * scale);
+        assert_near(y + height, CARD_HEIGHT_MM - 27.0 * CARD_HEIGHT_MM / IMAGE_SIZE.1);
+    }
+
+    #[test]
+    fn holes_are_cut_to_size_less_the_kerf() {
+        let layout = Layout::new(PageSize::CARD, Some(1), None, 0.0, false).unwrap();
+        let mut card = PunchCard::new();
+        card.columns[0] = vec![0];
+        card.columns[COLUMNS - 1] = vec![11];
+        let grid = Calibration::BUNDLED;
+        let options = CutOptions { corner_radius_mm: 0.0, kerf_mm: 0.1 };
+        let paths = sheet_paths(&layout, &[&card], &grid, IMAGE_SIZE, &options).unwrap();
+        assert_eq!(paths.len(), 3);
+        assert!(paths[2].outline);
+
+        // Left, right, bottom and top of a path
+        let bounds = |path: &CutPath| {
+            let (xs, ys) = (path.points.iter().map(|p| p.0), path.points.iter().map(|p| p.1));
+            (
+                xs.clone().fold(f32::MAX, f32::min),
+                xs.fold(f32::MIN, f32::max),
+                ys.clone().fold(f32::MAX, f32::min),
+                ys.fold(f32::MIN, f32::max),
+            )
+        };
+        let (x, y, width, height) = punch_rect(&grid, IMAGE_SIZE, 0, 0);
+        let (left, right, bottom, top) = bounds(&paths[0]);
+        assert_near(left, x + 0.05);
+        assert_near(right, x + width - 0.05);
+        assert_near(bottom, y + 0.05);
+        assert_near(top, y + height - 0.05);
+        let (x, y, width, _) = punch_rect(&grid, IMAGE_SIZE, COLUMNS - 1, 11);
+        let (left, right, bottom, _) = bounds(&paths[1]);
+        assert_near((left + right) / 2.0, x + width / 2.0);
+        assert_near(bottom, y + 0.05);
+
+        let too_wide = CutOptions { corner_radius_mm: 0.0, kerf_mm: 1.4 };
+        assert!(sheet_paths(&layout, &[&card], &grid, IMAGE_SIZE, &too_wide).is_err());
+    }
+
+    #[test]
+    fn dxf_is_r12() {
+        let dxf = dxf(&[CutPath { outline: true, points: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)] }]);
+        assert!(dxf.starts_with("0\nSECTION\n2\nHEADER\n9\n$ACADVER\n1\nAC1009\n0\nENDSEC\n"));
+        assert!(!dxf.contains("$INSUNITS"));
+        assert_eq!(dxf.matches("\nVERTEX\n").count(), 3);
+        assert!(dxf.ends_with("0\nEOF\n"));
+    }
+
+    #[test]
+    fn pdf_cut_contour_strokes_the_cut_file_punches() {
+        let mut deck = Deck::new();
+        deck.push_program("CUT", &["       PROGRAM-ID. CUT.".to_string()], 1);
+        let dir = std::env::temp_dir().join(format!("punchcard-cut-contour-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (pdf, sheet) = (dir.join("cut.pdf"), dir.join("cut.txt"));
+        let options = PdfOptions {
+            interpret: false,
+            interpreter_lines: Vec::new(),
+            vector_card: true,
+            rulings: Vec::new(),
+            printer: PrinterProfile::default(),
+            layout: Layout::new(PageSize::CARD, Some(1), None, 0.0, false).unwrap(),
+            crop_marks: false,
+            cut_contour: true,
+            cut_contour_outline: true,
+            corner_radius_mm: 0.0,
+        };
+        generate_punch_card_pdf(&deck, "", pdf.to_str().unwrap(), sheet.to_str().unwrap(), &options).unwrap();
+        let doc = Document::load(&pdf).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // The page names the spot colour as a separation
+        let (_, page_id) = doc.get_pages().into_iter().next().unwrap();
+        let (resources, _) = doc.get_page_resources(page_id);
+        let colour_space = resources
+            .unwrap()
+            .get(b"ColorSpace")
+            .and_then(Object::as_dict)
+            .and_then(|spaces| spaces.get(CUT_CONTOUR.as_bytes()))
+            .and_then(Object::as_array)
+            .unwrap();
+        assert_eq!(colour_space[0].as_name().unwrap(), b"Separation");
+        assert_eq!(colour_space[1].as_name().unwrap(), CUT_CONTOUR.as_bytes());
+
+        // Every punch is stroked, not filled, where the cut file cuts it
+        let content = Content::decode(&doc.get_page_content(page_id).unwrap()).unwrap();
+        let operators: Vec<&str> = content.operations.iter().map(|op| op.operator.as_str()).collect();
+        assert!(operators.contains(&"CS") && !operators.contains(&"f"));
+        let rects: Vec<usize> = (0..operators.len()).filter(|&idx| operators[idx] == "re").collect();
+        let card = &deck.punch_cards(&get_hollerith_encoding())[0];
+        let punches: Vec<(usize, usize)> = card
+            .columns
+            .iter()
+            .enumerate()
+            .flat_map(|(col_idx, rows)| rows.iter().map(move |&row| (col_idx, row)))
+            .collect();
+        assert_eq!(rects.len(), punches.len());
+        for (&idx, &(col_idx, row)) in rects.iter().zip(&punches) {
+            assert_eq!(operators[idx + 1], "S");
+            let drawn: Vec<f32> = content.operations[idx].operands.iter().map(|n| n.as_float().unwrap()).collect();
+            let (x, y, width, height) = punch_rect(&Calibration::BUNDLED, IMAGE_SIZE, col_idx, row);
+            for (drawn, mm) in drawn.into_iter().zip([x, y, width, height]) {
+                assert_near(drawn / PT_PER_MM, mm);
+            }
+        }
+
+        // The outline is the last path, closed and stroked
+        assert_eq!(operators[operators.len() - 3..], ["h", "S", "Q"]);
+    }
+}
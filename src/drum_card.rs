@@ -0,0 +1,254 @@
+// IBM 029 program (drum) cards: the card wrapped round the keypunch's
+// program drum that set out the fields of every data card punched under
+// it. Only program level one is read, from the punches in rows 12, 11, 0
+// and 1 of each column:
+//
+//   12   field definition: this column continues the field before it
+//   11   start of automatic skip: the field is left blank
+//   0    start of automatic duplication: the field repeats the last card
+//   1    alphabetic shift for this column; without it the column is numeric
+//   2    left-zero fill: a short numeric entry is right-aligned and padded
+//        with zeros (this tool's notation)
+//
+// Any column without a 12 starts a field, so a blank column is a one-column
+// numeric field keyed by hand. The program card is written as the text that
+// punches it: `1AAAAAAA` is an eight-column alphabetic field, `-&&&` a
+// four-column skip field, `0&&&&` a five-column duplicated field.
+
+use std::collections::HashMap;
+
+use crate::COLUMNS;
+
+/// Rows of the program-card codes, as indices into a column's punches
+const FIELD_DEFINITION: usize = 0;
+const AUTO_SKIP: usize = 1;
+const AUTO_DUPLICATE: usize = 2;
+const ALPHABETIC: usize = 3;
+const LEFT_ZERO: usize = 4;
+
+/// What the keypunch does at a field
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FieldAction {
+    /// Keyed by the operator
+    Manual,
+    /// Skipped over, left blank
+    Skip,
+    /// Copied from the same columns of the previous card unless keyed
+    Duplicate,
+}
+
+/// One field of the program card
+struct Field {
+    /// First column, 0-based
+    start: usize,
+    /// Alphabetic shift per column; numeric columns take digits only
+    alphabetic: Vec<bool>,
+    action: FieldAction,
+    left_zero: bool,
+}
+
+impl Field {
+    fn len(&self) -> usize {
+        self.alphabetic.len()
+    }
+
+    /// Card columns of the field, 1-based, for messages
+    fn columns(&self) -> String {
+        match self.len() {
+            1 => format!("column {}", self.start + 1),
+            len => format!("columns {}-{}", self.start + 1, self.start + len),
+        }
+    }
+}
+
+/// The fields of a program card, left to right
+pub struct ProgramCard {
+    fields: Vec<Field>,
+}
+
+/// Read a program card from its text: the first line that is neither blank
+/// nor a `*` comment
+pub fn parse(text: &str, encoding_map: &HashMap<char, Vec<usize>>) -> Result<ProgramCard, String> {
+    let line = text
+        .lines()
+        .find(|line| !line.trim().is_empty() && !line.starts_with('*'))
+        .ok_or("program card is empty")?
+        .trim_end();
+    if line.chars().count() > COLUMNS {
+        return Err(format!("program card is longer than {} columns", COLUMNS));
+    }
+
+    let mut fields: Vec<Field> = Vec::new();
+    for (col_idx, ch) in line.chars().enumerate() {
+        let uppercase_ch = ch.to_uppercase().next().unwrap_or(ch);
+        let punches = encoding_map
+            .get(&uppercase_ch)
+            .ok_or_else(|| format!("column {}: '{}' cannot be punched", col_idx + 1, ch))?;
+        let alphabetic = punches.contains(&ALPHABETIC);
+
+        if punches.contains(&FIELD_DEFINITION) {
+            let field = fields
+                .last_mut()
+                .ok_or("column 1: a field cannot start with a field-definition (12) punch")?;
+            field.alphabetic.push(alphabetic);
+            continue;
+        }
+
+        let action = match (punches.contains(&AUTO_SKIP), punches.contains(&AUTO_DUPLICATE)) {
+            (false, false) => FieldAction::Manual,
+            (true, false) => FieldAction::Skip,
+            (false, true) => FieldAction::Duplicate,
+            (true, true) => {
+                return Err(format!("column {}: a field cannot both skip (11) and duplicate (0)", col_idx + 1));
+            }
+        };
+        fields.push(Field {
+            start: col_idx,
+            alphabetic: vec![alphabetic],
+            action,
+            left_zero: punches.contains(&LEFT_ZERO),
+        });
+    }
+
+    for field in &fields {
+        if field.left_zero && field.alphabetic.iter().any(|&alpha| alpha) {
+            return Err(format!("{}: left-zero fill needs a numeric field", field.columns()));
+        }
+    }
+    Ok(ProgramCard { fields })
+}
+
+/// Place one keyed entry into its field, checking it against the shift
+fn fill_field(field: &Field, entry: &str, card: &mut [char]) -> Result<(), String> {
+    let entry: Vec<char> = entry.chars().map(|ch| ch.to_uppercase().next().unwrap_or(ch)).collect();
+    if entry.len() > field.len() {
+        return Err(format!(
+            "{}: '{}' is longer than the field's {} columns",
+            field.columns(),
+            entry.iter().collect::<String>(),
+            field.len()
+        ));
+    }
+
+    let padding = field.len() - entry.len();
+    let laid_out: Vec<char> = if field.left_zero {
+        std::iter::repeat_n('0', padding).chain(entry.iter().copied()).collect()
+    } else {
+        entry.iter().copied().chain(std::iter::repeat_n(' ', padding)).collect()
+    };
+
+    for (offset, (&ch, &alphabetic)) in laid_out.iter().zip(&field.alphabetic).enumerate() {
+        if !(alphabetic || ch.is_ascii_digit() || ch == ' ') {
+            return Err(format!(
+                "column {}: '{}' in numeric field ({}) is not a digit",
+                field.start + offset + 1,
+                ch,
+                field.columns()
+            ));
+        }
+        card[field.start + offset] = ch;
+    }
+    Ok(())
+}
+
+/// Lay out data lines into 80-column card text under the program card. Each
+/// line is one card holding the entries for its manual and duplicated
+/// fields, separated by tabs; skipped fields take no entry, and an empty
+/// entry in a duplicated field repeats the previous card.
+pub fn lay_out(
+    program: &ProgramCard,
+    data: &str,
+    encoding_map: &HashMap<char, Vec<usize>>,
+) -> Result<Vec<String>, String> {
+    let keyed: Vec<&Field> = program.fields.iter().filter(|f| f.action != FieldAction::Skip).collect();
+    let mut cards: Vec<Vec<char>> = Vec::new();
+
+    for (line_idx, line) in data.lines().enumerate() {
+        let card_num = line_idx + 1;
+        let entries: Vec<&str> = line.split('\t').collect();
+        if entries.len() > keyed.len() {
+            return Err(format!(
+                "card {}: {} entries for {} keyed fields",
+                card_num,
+                entries.len(),
+                keyed.len()
+            ));
+        }
+
+        let mut card = vec![' '; COLUMNS];
+        for (idx, field) in keyed.iter().enumerate() {
+            let entry = entries.get(idx).copied().unwrap_or("");
+            if field.action == FieldAction::Duplicate && entry.is_empty() {
+                if let Some(previous) = cards.last() {
+                    let range = field.start..field.start + field.len();
+                    card[range.clone()].copy_from_slice(&previous[range]);
+                }
+                continue;
+            }
+            fill_field(field, entry, &mut card).map_err(|err| format!("card {}: {}", card_num, err))?;
+        }
+
+        if let Some((col_idx, ch)) = card.iter().enumerate().find(|(_, ch)| !encoding_map.contains_key(ch)) {
+            return Err(format!("card {}: column {}: '{}' cannot be punched", card_num, col_idx + 1, ch));
+        }
+        cards.push(card);
+    }
+
+    Ok(cards.into_iter().map(|card| card.into_iter().collect()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_hollerith_encoding;
+
+    /// Name, then a zero-filled number, three skipped columns and a
+    /// duplicated code
+    const PROGRAM: &str = "1AAA2&&&-&&0&&";
+
+    #[test]
+    fn program_card_sets_out_the_fields() {
+        let encoding_map = get_hollerith_encoding();
+        let program = parse(&format!("* order cards\n\n{}\n", PROGRAM), &encoding_map).unwrap();
+        let fields: Vec<(usize, usize, bool, bool)> = program
+            .fields
+            .iter()
+            .map(|field| (field.start, field.len(), field.action == FieldAction::Skip, field.left_zero))
+            .collect();
+        assert_eq!(fields, [(0, 4, false, false), (4, 4, false, true), (8, 3, true, false), (11, 3, false, false)]);
+        assert!(program.fields[0].alphabetic.iter().all(|&alpha| alpha));
+        assert!(program.fields[3].action == FieldAction::Duplicate);
+        assert!(!program.fields[3].alphabetic.iter().any(|&alpha| alpha));
+    }
+
+    #[test]
+    fn data_is_laid_out_under_the_program() {
+        let encoding_map = get_hollerith_encoding();
+        let program = parse(PROGRAM, &encoding_map).unwrap();
+        let cards = lay_out(&program, "bolt\t42\t123\nNUT\t7\n\t\t9", &encoding_map).unwrap();
+        let cards: Vec<&str> = cards.iter().map(|card| card.trim_end()).collect();
+        // The empty duplicated field repeats the card before
+        assert_eq!(cards, ["BOLT0042   123", "NUT 0007   123", "    0000   9"]);
+
+        let Err(err) = lay_out(&program, "BOLT\t4X", &encoding_map) else { panic!("a letter in a numeric field") };
+        assert_eq!(err, "card 1: column 8: 'X' in numeric field (columns 5-8) is not a digit");
+        let Err(err) = lay_out(&program, "BOLTS", &encoding_map) else { panic!("an entry longer than its field") };
+        assert!(err.contains("longer than the field's 4 columns"), "{}", err);
+        let Err(err) = lay_out(&program, "A\t1\t2\t3", &encoding_map) else { panic!("an entry for the skipped field") };
+        assert_eq!(err, "card 1: 4 entries for 3 keyed fields");
+    }
+
+    #[test]
+    fn bad_program_cards_are_refused() {
+        let mut encoding_map = get_hollerith_encoding();
+        let Err(err) = parse("&AAA", &encoding_map) else { panic!("a field starting with 12") };
+        assert!(err.contains("cannot start with a field-definition (12) punch"), "{}", err);
+        let Err(err) = parse("2AA", &encoding_map) else { panic!("left-zero fill on letters") };
+        assert_eq!(err, "columns 1-3: left-zero fill needs a numeric field");
+
+        // No character is punched 11-0, but a multi-punched column can be
+        encoding_map.insert('~', vec![AUTO_SKIP, AUTO_DUPLICATE]);
+        let Err(err) = parse("11~&", &encoding_map) else { panic!("a field both skipped and duplicated") };
+        assert_eq!(err, "column 3: a field cannot both skip (11) and duplicate (0)");
+    }
+}
@@ -0,0 +1,105 @@
+// Code page 037 (US/Canada EBCDIC) for the ASCII range. Characters outside
+// it have no place on a card and become the EBCDIC substitute character.
+
+/// EBCDIC SUB, used for characters CP037 has no code for here
+pub const SUBSTITUTE: u8 = 0x3F;
+
+/// EBCDIC blank
+pub const SPACE: u8 = 0x40;
+
+// CP037 code for each ASCII character 0x00-0x7F
+const ASCII_TO_CP037: [u8; 128] = [
+    0x00, 0x01, 0x02, 0x03, 0x37, 0x2D, 0x2E, 0x2F, 0x16, 0x05, 0x25, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+    0x10, 0x11, 0x12, 0x13, 0x3C, 0x3D, 0x32, 0x26, 0x18, 0x19, 0x3F, 0x27, 0x1C, 0x1D, 0x1E, 0x1F,
+    0x40, 0x5A, 0x7F, 0x7B, 0x5B, 0x6C, 0x50, 0x7D, 0x4D, 0x5D, 0x5C, 0x4E, 0x6B, 0x60, 0x4B, 0x61,
+    0xF0, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9, 0x7A, 0x5E, 0x4C, 0x7E, 0x6E, 0x6F,
+    0x7C, 0xC1, 0xC2, 0xC3, 0xC4, 0xC5, 0xC6, 0xC7, 0xC8, 0xC9, 0xD1, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6,
+    0xD7, 0xD8, 0xD9, 0xE2, 0xE3, 0xE4, 0xE5, 0xE6, 0xE7, 0xE8, 0xE9, 0xBA, 0xE0, 0xBB, 0xB0, 0x6D,
+    0x79, 0x81, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x91, 0x92, 0x93, 0x94, 0x95, 0x96,
+    0x97, 0x98, 0x99, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8, 0xA9, 0xC0, 0x4F, 0xD0, 0xA1, 0x07,
+];
+
+/// EBCDIC code for a character
+pub fn from_char(ch: char) -> u8 {
+    if ch.is_ascii() {
+        ASCII_TO_CP037[ch as usize]
+    } else {
+        SUBSTITUTE
+    }
+}
+
+/// Encode text as EBCDIC, one byte per character
+pub fn encode(text: &str) -> Vec<u8> {
+    text.chars().map(from_char).collect()
+}
+
+/// Character for an EBCDIC code, for the codes CP037 shares with ASCII
+pub fn to_char(byte: u8) -> Option<char> {
+    ASCII_TO_CP037.iter().position(|&code| code == byte).map(|ascii| ascii as u8 as char)
+}
+
+// Punches as 12-bit column values, row 12 in the top bit down to row 9
+const R12: u16 = 0x800;
+const R11: u16 = 0x400;
+const R0: u16 = 0x200;
+const R8: u16 = 0x002;
+const R9: u16 = 0x001;
+
+/// Punch for digit row 1 to 9
+const fn digit(d: u8) -> u16 {
+    0x100 >> (d - 1)
+}
+
+// Zone punches by high nibble, for low nibbles 1-9 and for A-F
+const ZONES_1_9: [u16; 16] = [
+    R12 | R9, R11 | R9, R0 | R9, R9,
+    R12 | R0 | R9, R12 | R11 | R9, R11 | R0 | R9, R12 | R11 | R0 | R9,
+    R12 | R0, R12 | R11, R11 | R0, R12 | R11 | R0,
+    R12, R11, R0, 0,
+];
+const ZONES_A_F: [u16; 16] = [
+    R12 | R9, R11 | R9, R0 | R9, R9,
+    R12, R11, R0, 0,
+    R12 | R0, R12 | R11, R11 | R0, R12 | R11 | R0,
+    R12 | R0 | R9, R12 | R11 | R9, R11 | R0 | R9, R12 | R11 | R0 | R9,
+];
+
+/// System/360 card code for an EBCDIC byte: the punches a card reader
+/// translates into that byte. Every one of the 256 codes has its own pattern.
+pub fn card_code(byte: u8) -> u16 {
+    let hi = (byte >> 4) as usize;
+    let lo = byte & 0x0F;
+    match (byte, lo) {
+        // Slash and 0xE1 trade places with the regular pattern
+        (0x61, _) => R0 | digit(1),
+        (0xE1, _) => R11 | R0 | R9 | digit(1),
+        (0x6A, _) => R12 | R11,
+        (_, 0) => [
+            R12 | R0 | R9 | R8 | digit(1),
+            R12 | R11 | R9 | R8 | digit(1),
+            R11 | R0 | R9 | R8 | digit(1),
+            R12 | R11 | R0 | R9 | R8 | digit(1),
+            0,
+            R12,
+            R11,
+            R12 | R11 | R0,
+            R12 | R0 | R8 | digit(1),
+            R12 | R11 | R8 | digit(1),
+            R11 | R0 | R8 | digit(1),
+            R12 | R11 | R0 | R8 | digit(1),
+            R12 | R0,
+            R11 | R0,
+            R0 | R8 | digit(2),
+            R0,
+        ][hi],
+        // Below 0x80 a low nibble of 9 leads the 8-2 to 8-7 run of A-F as 8-1
+        (_, 9) if hi < 8 => ZONES_A_F[hi] | R8 | digit(1),
+        (_, 1..=9) => ZONES_1_9[hi] | digit(lo),
+        _ => ZONES_A_F[hi] | R8 | digit(lo - 8),
+    }
+}
+
+/// EBCDIC byte for a card code, if the punches form a valid one
+pub fn from_card_code(code: u16) -> Option<u8> {
+    (0..=255u8).find(|&byte| card_code(byte) == code)
+}
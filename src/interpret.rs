@@ -0,0 +1,174 @@
+// Printing a card's characters on the card itself, so it can be read by
+// eye: along the top edge over each column, as the IBM 029 printed while it
+// punched, or in the IBM 557 interpreter's larger type on chosen lines
+// across the card.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use lopdf::Object;
+
+use crate::calibration::Calibration;
+use crate::COLUMNS;
+
+/// Font resource names in the page: Courier for what is punched, red
+/// Courier Oblique for columns that could not be
+pub const FONT: &str = "F1";
+pub const UNPUNCHED_FONT: &str = "F2";
+
+/// Courier glyphs are 600/1000 of the font size wide
+const COURIER_ADVANCE: f32 = 0.6;
+/// The 557 printed at most 60 characters on a line
+pub const INTERPRETER_POSITIONS: usize = 60;
+/// Baseline of the 029's printing above the 12 row, as a fraction of the
+/// row spacing
+const TOP_EDGE_BASELINE: f32 = 0.2;
+
+/// One line of 557 printing: the card row it runs along and the columns it
+/// shows, 1-based
+#[derive(Clone, Debug)]
+pub struct InterpreterLine {
+    pub row: usize,
+    pub first: usize,
+    pub last: usize,
+}
+
+impl FromStr for InterpreterLine {
+    type Err = String;
+
+    /// `ROW` or `ROW:FIRST-LAST`, rows named 12, 11 and 0 to 9, e.g. `12`
+    /// or `11:61-80`; by default the first 60 columns
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (row_name, columns) = match text.split_once(':') {
+            Some((row, columns)) => (row.trim(), Some(columns)),
+            None => (text.trim(), None),
+        };
+        let row = match crate::parse_punch_pattern(row_name).as_deref() {
+            Some(&[row]) => row,
+            _ => return Err(format!("no card row {}; rows are 12, 11 and 0 to 9", row_name)),
+        };
+
+        let (first, last) = match columns {
+            None => (1, INTERPRETER_POSITIONS),
+            Some(range) => {
+                let (first, last) = range
+                    .split_once('-')
+                    .ok_or_else(|| format!("columns {} are not a FIRST-LAST range", range))?;
+                let parse = |n: &str| n.trim().parse::<usize>().map_err(|_| format!("bad column {}", n));
+                (parse(first)?, parse(last)?)
+            }
+        };
+        if first == 0 || first > last || last > COLUMNS {
+            return Err(format!("columns {}-{} are not within 1-{}", first, last, COLUMNS));
+        }
+        if last + 1 - first > INTERPRETER_POSITIONS {
+            return Err(format!("the 557 prints at most {} columns on a line", INTERPRETER_POSITIONS));
+        }
+        Ok(InterpreterLine { row, first, last })
+    }
+}
+
+/// What gets printed for a column: the character as punched, or, in the
+/// unpunched style, the character the keypunch could not punch
+fn printed_column(ch: char, encoding_map: &HashMap<char, Vec<usize>>) -> (u8, bool) {
+    let uppercase_ch = ch.to_uppercase().next().unwrap_or(ch);
+    if encoding_map.contains_key(&uppercase_ch) {
+        (uppercase_ch as u8, true)
+    } else if ch.is_ascii_graphic() {
+        (ch as u8, false)
+    } else {
+        (b'?', false)
+    }
+}
+
+/// A character as a PDF string operand, escaped
+fn pdf_string(byte: u8) -> Object {
+    let bytes = match byte {
+        b'(' | b')' | b'\\' => vec![b'\\', byte],
+        _ => vec![byte],
+    };
+    Object::String(bytes, lopdf::StringFormat::Literal)
+}
+
+/// Text operations printing one card on the given punch grid. `place`
+/// takes a point in template pixels to page coordinates, and `scale` is
+/// points per template pixel across the card.
+pub fn card_operations(
+    text: &str,
+    top_edge: bool,
+    lines: &[InterpreterLine],
+    grid: &Calibration,
+    encoding_map: &HashMap<char, Vec<usize>>,
+    place: impl Fn(f32, f32) -> (f32, f32),
+    scale: f32,
+) -> Vec<(String, Vec<Object>)> {
+    let columns: Vec<char> = text.chars().chain(std::iter::repeat(' ')).take(COLUMNS).collect();
+    let mut operations = vec![("BT".to_string(), vec![])];
+    let mut current_font = None;
+
+    let mut print = |operations: &mut Vec<(String, Vec<Object>)>, ch: char, x_px: f32, y_px: f32, size: f32| {
+        if ch == ' ' {
+            return;
+        }
+        let (byte, punchable) = printed_column(ch, encoding_map);
+        let font = if punchable { FONT } else { UNPUNCHED_FONT };
+        if current_font != Some((font, size.to_bits())) {
+            current_font = Some((font, size.to_bits()));
+            let red = if punchable { 0.0 } else { 0.8 };
+            operations.push(("Tf".to_string(), vec![Object::Name(font.as_bytes().to_vec()), size.into()]));
+            operations.push(("rg".to_string(), vec![red.into(), 0.0.into(), 0.0.into()]));
+        }
+        let (x, y) = place(x_px, y_px);
+        operations.push(("Tm".to_string(), vec![1.into(), 0.into(), 0.into(), 1.into(), x.into(), y.into()]));
+        operations.push(("Tj".to_string(), vec![pdf_string(byte)]));
+    };
+
+    // 029: one character centred over each column, as wide as the column
+    if top_edge {
+        let size = grid.column_spacing * scale / COURIER_ADVANCE;
+        let baseline = grid.first_punch_y - TOP_EDGE_BASELINE * grid.row_spacing;
+        for (col_idx, &ch) in columns.iter().enumerate() {
+            let centre = grid.punch_left(col_idx) + grid.punch_width / 2.0;
+            print(&mut operations, ch, centre - grid.column_spacing / 2.0, baseline, size);
+        }
+    }
+
+    // 557: sixty print positions spread over the width of the punch field,
+    // along the chosen row
+    let pitch = (COLUMNS - 1) as f32 * grid.column_spacing / INTERPRETER_POSITIONS as f32;
+    let size = pitch * scale / COURIER_ADVANCE;
+    for line in lines {
+        let baseline = grid.punch_top(line.row) + grid.punch_height * 0.85;
+        for (position, &ch) in columns[line.first - 1..line.last].iter().enumerate() {
+            print(&mut operations, ch, grid.first_punch_x + position as f32 * pitch, baseline, size);
+        }
+    }
+
+    operations.push(("ET".to_string(), vec![]));
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> (usize, usize, usize) {
+        let line: InterpreterLine = text.parse().unwrap();
+        (line.row, line.first, line.last)
+    }
+
+    #[test]
+    fn interpreter_lines_parse() {
+        assert_eq!(line("12"), (0, 1, INTERPRETER_POSITIONS));
+        assert_eq!(line("11:61-80"), (1, 61, 80));
+        assert_eq!(line(" 0 : 1-60"), (2, 1, 60));
+        assert_eq!(line("9:21-21"), (11, 21, 21));
+    }
+
+    #[test]
+    fn bad_interpreter_lines_are_refused() {
+        for text in ["13", "12-11", "", "x", "5:1", "5:0-10", "5:20-10", "5:70-81", "5:1-61", "5:a-b"] {
+            assert!(text.parse::<InterpreterLine>().is_err(), "{} parsed", text);
+        }
+    }
+}
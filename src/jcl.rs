@@ -0,0 +1,788 @@
+use std::str::FromStr;
+
+use clap::ValueEnum;
+
+/// Marker line where the COBOL source cards are spliced into the job
+pub const SOURCE_MARKER: &str = "COBOL SOURCE CARDS FOLLOW";
+
+/// What the generated job does with the source deck
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum JclKind {
+    /// Compile, link-edit and run the program
+    Compile,
+    /// Add the source as a member of a partitioned dataset with IEBUPDTE
+    Iebupdte,
+    /// Copy the source into a sequential dataset with IEBGENER
+    Iebgener,
+}
+
+impl JclKind {
+    /// Step between card sequence numbers. IEBUPDTE decks are numbered in
+    /// tens so later `./ CHANGE` runs can insert cards between existing ones.
+    pub fn sequence_increment(self) -> usize {
+        match self {
+            JclKind::Iebupdte => 10,
+            _ => 1,
+        }
+    }
+}
+
+/// How the jobs for several programs are laid out in one deck
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum JobLayout {
+    /// One job: a step per program and, when compiling, one shared link-edit
+    Combined,
+    /// A complete job per program, stacked one after another
+    Separate,
+}
+
+/// Operating system and compiler the jobs are written for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Dialect {
+    /// z/OS with Enterprise COBOL (IGYCRCTL) and Language Environment
+    Zos,
+    /// MVS 3.8j under Hercules (TK4-) with ANS COBOL (IKFCBL00)
+    Mvs38j,
+}
+
+/// Built-in site profiles for the generated jobs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Profile {
+    /// Installation default compiler options
+    Default,
+    /// Listings, runtime checks and debugging hooks, no optimisation
+    Debug,
+    /// Optimised production build
+    Optimize,
+}
+
+/// Compiler options and datasets a profile puts into the job
+#[derive(Clone, Debug)]
+pub struct JclProfile {
+    pub dialect: Dialect,
+    /// High-level qualifier for the datasets the jobs create
+    pub hlq: String,
+    /// Output class for listings and program output
+    pub sysout_class: String,
+    /// Options for the compile step's PARM=, empty for installation defaults
+    pub compile_options: Vec<String>,
+    pub compiler_library: String,
+    pub link_library: String,
+    pub runtime_library: String,
+    /// DB2 load library with the precompiler and the language interface
+    pub db2_load_library: String,
+    /// Library the DBRM for each SQL program is written into
+    pub dbrm_library: String,
+    /// DCLGEN members for EXEC SQL INCLUDE
+    pub sql_include_library: String,
+    /// Use the compiler's integrated SQL coprocessor instead of DSNHPC
+    pub sql_coprocessor: bool,
+    /// CICS load library with the translator and the EXEC interface stub
+    pub cics_load_library: String,
+    /// Libraries searched for called modules that are not in the deck
+    pub call_libraries: Vec<CallLibrary>,
+    /// Punch the deck even when calls cannot be resolved, leaving them for
+    /// the link-edit step to report
+    pub allow_unresolved_calls: bool,
+}
+
+/// A load or object library the link-edit step can search for called modules
+#[derive(Clone, Debug)]
+pub struct CallLibrary {
+    pub dataset: String,
+    /// Modules known to be in the library; empty if it is trusted to hold
+    /// any module the programs call
+    pub members: Vec<String>,
+}
+
+impl CallLibrary {
+    fn provides(&self, module: &str) -> bool {
+        self.members.is_empty() || self.members.iter().any(|m| m == module)
+    }
+}
+
+impl FromStr for CallLibrary {
+    type Err = String;
+
+    /// `DSNAME` or, JCL style, `DSNAME(MEMBER1,MEMBER2)`
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim().to_uppercase();
+        let (dataset, members) = match text.split_once('(') {
+            Some((dataset, rest)) => {
+                let list = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("missing ')' in library {}", text))?;
+                (dataset.to_string(), list.split(',').map(|m| mvs_name(m.trim())).collect())
+            }
+            None => (text.clone(), Vec::new()),
+        };
+
+        if dataset.is_empty() {
+            return Err("library dataset name is empty".to_string());
+        }
+        Ok(CallLibrary { dataset, members })
+    }
+}
+
+impl Profile {
+    pub fn settings(self, dialect: Dialect) -> JclProfile {
+        let compile_options: &[&str] = match self {
+            Profile::Default => &[],
+            Profile::Debug => &["SOURCE", "XREF", "MAP", "LIST", "TEST", "NOOPTIMIZE", "SSRANGE"],
+            Profile::Optimize => &["SOURCE", "OPTIMIZE(2)", "NOTEST", "NOSSRANGE"],
+        };
+
+        let mut profile = JclProfile {
+            dialect,
+            hlq: "&SYSUID.".to_string(),
+            sysout_class: "*".to_string(),
+            compile_options: compile_options.iter().map(|o| o.to_string()).collect(),
+            compiler_library: "IGY.V6R3M0.SIGYCOMP".to_string(),
+            link_library: "CEE.SCEELKED".to_string(),
+            runtime_library: "CEE.SCEERUN".to_string(),
+            db2_load_library: "DSNC10.SDSNLOAD".to_string(),
+            dbrm_library: "DSNC10.DBRMLIB.DATA".to_string(),
+            sql_include_library: "&SYSUID..DCLGEN".to_string(),
+            sql_coprocessor: false,
+            cics_load_library: "DFH610.CICS.SDFHLOAD".to_string(),
+            call_libraries: Vec::new(),
+            allow_unresolved_calls: false,
+        };
+
+        if dialect == Dialect::Mvs38j {
+            // No JCL symbols and no SYSOUT=* on MVS 3.8j. TK4- ships the
+            // HERC01 user, prints class A, and the COBOL subroutine library
+            // is all the linkage editor needs.
+            profile.hlq = "HERC01".to_string();
+            profile.sysout_class = "A".to_string();
+            profile.compiler_library = "SYS1.LINKLIB".to_string();
+            profile.link_library = "SYS1.COBLIB".to_string();
+            profile.runtime_library = "SYS1.COBLIB".to_string();
+        }
+
+        profile
+    }
+}
+
+/// What the job needs to know about a program in the deck
+#[derive(Clone, Debug)]
+pub struct ProgramInfo {
+    /// Name from the PROGRAM-ID paragraph
+    pub name: String,
+    /// Number of source cards
+    pub card_count: usize,
+    /// Options for this program's compile step, the profile's merged with
+    /// those on its CBL/PROCESS statements
+    pub compile_options: Vec<String>,
+    /// The source contains EXEC SQL blocks for DB2
+    pub uses_sql: bool,
+    /// The source contains EXEC CICS commands
+    pub uses_cics: bool,
+    /// Targets of static CALL 'literal' statements, in order of first use
+    pub calls: Vec<String>,
+}
+
+/// How the link-edit step finds the modules the programs call
+struct LinkPlan {
+    /// Programs in the job that are called, by index, to be INCLUDEd
+    includes: Vec<usize>,
+    /// Libraries to concatenate to SYSLIB for modules outside the job
+    libraries: Vec<String>,
+    /// `caller -> module` for calls nothing provides
+    unresolved: Vec<String>,
+    /// Programs in the job that nothing calls, left out of the load module
+    uncalled: Vec<String>,
+}
+
+/// Work out where every called module comes from. The job's own programs
+/// come first, then the runtime libraries the job already uses, then the
+/// configured call libraries in order.
+fn plan_link(programs: &[ProgramInfo], profile: &JclProfile) -> LinkPlan {
+    let names: Vec<String> = programs.iter().map(|p| mvs_name(&p.name)).collect();
+    let uses_sql = programs.iter().any(|p| p.uses_sql);
+    let uses_cics = programs.iter().any(|p| p.uses_cics);
+    let mut plan = LinkPlan {
+        includes: Vec::new(),
+        libraries: Vec::new(),
+        unresolved: Vec::new(),
+        uncalled: Vec::new(),
+    };
+
+    for program in programs {
+        for call in &program.calls {
+            let module = mvs_name(call);
+
+            if let Some(index) = names.iter().position(|n| *n == module) {
+                if index > 0 && !plan.includes.contains(&index) {
+                    plan.includes.push(index);
+                }
+                continue;
+            }
+
+            // Language Environment, ANS COBOL library, DB2 and CICS services
+            let runtime = module.starts_with("CEE")
+                || module.starts_with("IGZ")
+                || module.starts_with("ILBO")
+                || (uses_sql && module.starts_with("DSN"))
+                || (uses_cics && module.starts_with("DFH"));
+            if runtime {
+                continue;
+            }
+
+            match profile.call_libraries.iter().find(|lib| lib.provides(&module)) {
+                Some(library) => {
+                    if !plan.libraries.contains(&library.dataset) {
+                        plan.libraries.push(library.dataset.clone());
+                    }
+                }
+                None => plan.unresolved.push(format!("{} -> {}", program.name, call)),
+            }
+        }
+    }
+
+    plan.includes.sort();
+    plan.uncalled = (1..programs.len())
+        .filter(|index| !plan.includes.contains(index))
+        .map(|index| programs[index].name.clone())
+        .collect();
+    plan
+}
+
+/// Problems the link-edit step of the compile jobs will run into. Calls no
+/// program or library provides are an error, unless the profile allows
+/// them; they and programs nothing calls are otherwise given as warnings.
+pub fn check_calls(programs: &[ProgramInfo], profile: &JclProfile, layout: JobLayout) -> Result<Vec<String>, String> {
+    let jobs: Vec<&[ProgramInfo]> = match layout {
+        JobLayout::Combined => vec![programs],
+        JobLayout::Separate => programs.chunks(1).collect(),
+    };
+
+    let mut unresolved = Vec::new();
+    let mut problems = Vec::new();
+    for job in jobs {
+        let plan = plan_link(job, profile);
+        unresolved.extend(plan.unresolved);
+        problems.extend(
+            plan.uncalled
+                .into_iter()
+                .map(|name| format!("{} is not called by any program in the job and will not be linked", name)),
+        );
+    }
+    if !unresolved.is_empty() && !profile.allow_unresolved_calls {
+        return Err(format!(
+            "unresolved calls {}; add the library with --call-library, or punch anyway with --allow-unresolved-calls",
+            unresolved.join(", ")
+        ));
+    }
+    problems.extend(unresolved.into_iter().map(|call| format!("unresolved call {}", call)));
+    Ok(problems)
+}
+
+/// Longest PARM= value the system passes to a program
+pub const MAX_PARM_LENGTH: usize = 100;
+
+/// PARM= continuation cards for a list of options. A short list fits on
+/// one card as `PARM='A,B'`; a longer one is split into quoted pieces,
+/// `PARM=('A,B',` / `'C')`, which the system joins back with commas.
+/// `keyword` is `PARM`, or `PARM.step` when overriding a procedure step.
+fn parm_cards(keyword: &str, options: &[String]) -> Vec<String> {
+    const INDENT: &str = "//             ";
+    // Statements end in column 71
+    const LIMIT: usize = 71;
+
+    let quoted: Vec<String> = options.iter().map(|o| o.replace('\'', "''")).collect();
+    let single = format!("{}{}='{}'", INDENT, keyword, quoted.join(","));
+    if single.len() <= LIMIT {
+        return vec![single];
+    }
+
+    let mut cards = Vec::new();
+    let mut current = format!("{}{}=('", INDENT, keyword);
+    let mut first_in_card = true;
+    for option in &quoted {
+        // Room for the option, the closing quote and a comma or parenthesis
+        if !first_in_card && current.len() + 1 + option.len() + 2 > LIMIT {
+            current.push_str("',");
+            cards.push(std::mem::replace(&mut current, format!("{}'", INDENT)));
+            first_in_card = true;
+        }
+        if !first_in_card {
+            current.push(',');
+        }
+        current.push_str(option);
+        first_in_card = false;
+    }
+    current.push_str("')");
+    cards.push(current);
+    cards
+}
+
+/// Generate the jobs wrapping the source decks for the selected kind. The
+/// source marker appears once per program, in the order given.
+pub fn generate(kind: JclKind, programs: &[ProgramInfo], profile: &JclProfile, layout: JobLayout) -> Vec<String> {
+    if layout == JobLayout::Separate && programs.len() > 1 {
+        return programs
+            .iter()
+            .flat_map(|program| generate(kind, std::slice::from_ref(program), profile, JobLayout::Combined))
+            .collect();
+    }
+
+    match (kind, profile.dialect) {
+        (JclKind::Compile, Dialect::Zos) => generate_jcl(programs, profile),
+        (JclKind::Compile, Dialect::Mvs38j) => generate_mvs38j_jcl(programs, profile),
+        (JclKind::Iebupdte, _) => generate_iebupdte_jcl(programs, profile),
+        (JclKind::Iebgener, _) => generate_iebgener_jcl(programs, profile),
+    }
+}
+
+/// Turn a COBOL program name into a valid MVS name (member, job or
+/// dataset qualifier) the way the compilers name the object module: at
+/// most 8 characters, hyphens made `0`, and a leading digit made a letter
+/// (`0` to `J`, `1`-`9` to `A`-`I`)
+pub fn mvs_name(program_name: &str) -> String {
+    let mut name: String = program_name
+        .to_uppercase()
+        .chars()
+        .filter_map(|c| match c {
+            '-' => Some('0'),
+            c if c.is_ascii_alphanumeric() || matches!(c, '$' | '#' | '@') => Some(c),
+            _ => None,
+        })
+        .take(8)
+        .collect();
+
+    if name.is_empty() {
+        name = "COBPROG".to_string();
+    } else if let Some(digit) = name.chars().next().and_then(|c| c.to_digit(10)) {
+        let letter = if digit == 0 { 'J' } else { (b'A' + digit as u8 - 1) as char };
+        name.replace_range(..1, &letter.to_string());
+    }
+    name
+}
+
+/// Dataset the source deck is stored into by the IEBUPDTE and IEBGENER jobs
+pub fn source_dataset(program_name: &str, profile: &JclProfile) -> String {
+    format!("{}.{}.COBOL", profile.hlq, mvs_name(program_name))
+}
+
+/// Step name, numbered when the job has a step like it for every program
+fn step_name(base: &str, index: usize, program_count: usize) -> String {
+    if program_count == 1 {
+        base.to_string()
+    } else {
+        format!("{}{}", base, index + 1)
+    }
+}
+
+/// JOB statement and its continuation. MVS 3.8j has no &SYSUID to notify.
+fn job_card(program_name: &str, title: &str, profile: &JclProfile) -> Vec<String> {
+    let continuation = match profile.dialect {
+        Dialect::Zos => "//             MSGLEVEL=(1,1),NOTIFY=&SYSUID",
+        Dialect::Mvs38j => "//             MSGLEVEL=(1,1)",
+    };
+    vec![
+        format!("//{:<8} JOB (ACCT),'{}',CLASS=A,MSGCLASS=A,", mvs_name(program_name), title),
+        continuation.to_string(),
+    ]
+}
+
+/// Printed output DD statement for the profile's output class
+fn sysout(ddname: &str, profile: &JclProfile) -> String {
+    format!("//{:<8} DD SYSOUT={}", ddname, profile.sysout_class)
+}
+
+/// In-stream source cards for the first step of the job
+fn source_cards(jcl: &mut Vec<String>, card_count: usize) {
+    jcl.push("//SYSIN    DD *".to_string());
+    jcl.push(format!("//* {} {}", card_count, SOURCE_MARKER));
+    jcl.push("/*".to_string());
+}
+
+/// DB2 precompile step: DSNHPC turns EXEC SQL blocks into calls and writes
+/// the DBRM for the later BIND
+fn db2_precompile_step(jcl: &mut Vec<String>, step: &str, program: &ProgramInfo, profile: &JclProfile) {
+    jcl.push("//*".to_string());
+    jcl.push(format!("//{:<8} EXEC PGM=DSNHPC,PARM='HOST(IBMCOB)',REGION=4096K", step));
+    jcl.push(format!("//STEPLIB  DD DSNAME={},DISP=SHR", profile.db2_load_library));
+    jcl.push(format!("//DBRMLIB  DD DSNAME={}({}),DISP=SHR", profile.dbrm_library, mvs_name(&program.name)));
+    jcl.push(format!("//SYSLIB   DD DSNAME={},DISP=SHR", profile.sql_include_library));
+    jcl.push("//SYSCIN   DD DSNAME=&&DSNHOUT,DISP=(MOD,PASS),".to_string());
+    jcl.push("//            UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSPRINT DD SYSOUT=*".to_string());
+    jcl.push("//SYSTERM  DD SYSOUT=*".to_string());
+    jcl.push("//SYSUT1   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSUT2   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    source_cards(jcl, program.card_count);
+}
+
+/// CICS translate step: DFHECP1$ turns EXEC CICS commands into calls
+fn cics_translate_step(jcl: &mut Vec<String>, step: &str, program: &ProgramInfo, profile: &JclProfile, input: Option<&str>) {
+    jcl.push("//*".to_string());
+    jcl.push(format!("//{:<8} EXEC PGM=DFHECP1$,REGION=0M", step));
+    jcl.push(format!("//STEPLIB  DD DSNAME={},DISP=SHR", profile.cics_load_library));
+    jcl.push("//SYSPRINT DD SYSOUT=*".to_string());
+    jcl.push("//SYSPUNCH DD DSNAME=&&SYSCIN,DISP=(NEW,PASS),".to_string());
+    jcl.push("//            UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    match input {
+        Some(dataset) => jcl.push(format!("//SYSIN    DD DSNAME={},DISP=(OLD,DELETE)", dataset)),
+        None => source_cards(jcl, program.card_count),
+    }
+}
+
+/// Temporary dataset holding a program's object code. With several programs
+/// each gets its own, so the link-edit can include only those it needs.
+fn object_dataset(index: usize, program_count: usize) -> String {
+    if program_count == 1 {
+        "&&LOADSET".to_string()
+    } else {
+        format!("&&OBJ{}", index + 1)
+    }
+}
+
+/// Steps that turn one program's source cards into object code
+fn compile_steps(jcl: &mut Vec<String>, index: usize, programs: &[ProgramInfo], profile: &JclProfile) {
+    let program = &programs[index];
+    let precompile_sql = program.uses_sql && !profile.sql_coprocessor;
+    let coprocess_sql = program.uses_sql && profile.sql_coprocessor;
+
+    // DB2 precompile runs before the CICS translator, which runs before
+    // the compiler. Whichever comes first reads the source cards and each
+    // step hands its output to the next in a temporary dataset.
+    let mut compiler_input = None;
+    if precompile_sql {
+        db2_precompile_step(jcl, &step_name("PC", index, programs.len()), program, profile);
+        compiler_input = Some("&&DSNHOUT");
+    }
+    if program.uses_cics {
+        cics_translate_step(jcl, &step_name("TRN", index, programs.len()), program, profile, compiler_input);
+        compiler_input = Some("&&SYSCIN");
+    }
+
+    let mut compile_options = program.compile_options.clone();
+    if coprocess_sql && !compile_options.iter().any(|o| o == "SQL" || o.starts_with("SQL(")) {
+        compile_options.push("SQL".to_string());
+    }
+    let step = if programs.len() == 1 { "COMPILE".to_string() } else { step_name("COMP", index, programs.len()) };
+
+    jcl.push("//*".to_string());
+    if compile_options.is_empty() {
+        jcl.push(format!("//{:<8} EXEC PGM=IGYCRCTL,REGION=0M", step));
+    } else {
+        jcl.push(format!("//{:<8} EXEC PGM=IGYCRCTL,REGION=0M,", step));
+        jcl.extend(parm_cards("PARM", &compile_options));
+    }
+    jcl.push(format!("//STEPLIB  DD DSNAME={},DISP=SHR", profile.compiler_library));
+    if coprocess_sql {
+        // The coprocessor is loaded from DB2 and writes the DBRM itself
+        jcl.push(format!("//         DD DSNAME={},DISP=SHR", profile.db2_load_library));
+        jcl.push(format!("//DBRMLIB  DD DSNAME={}({}),DISP=SHR", profile.dbrm_library, mvs_name(&program.name)));
+        jcl.push(format!("//SYSLIB   DD DSNAME={},DISP=SHR", profile.sql_include_library));
+    }
+    jcl.push("//SYSPRINT DD SYSOUT=*".to_string());
+    jcl.push(format!("//SYSLIN   DD DSNAME={},DISP=(MOD,PASS),", object_dataset(index, programs.len())));
+    jcl.push("//            UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSUT1   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSUT2   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSUT3   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSUT4   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSUT5   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSUT6   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSUT7   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    match compiler_input {
+        Some(dataset) => jcl.push(format!("//SYSIN    DD DSNAME={},DISP=(OLD,DELETE)", dataset)),
+        None => source_cards(jcl, program.card_count),
+    }
+}
+
+/// Generate JCL for compiling the programs, link-editing them into one
+/// load module and running it. The first program is the main program.
+pub fn generate_jcl(programs: &[ProgramInfo], profile: &JclProfile) -> Vec<String> {
+    let mut jcl = Vec::new();
+    let uses_sql = programs.iter().any(|p| p.uses_sql);
+    let uses_cics = programs.iter().any(|p| p.uses_cics);
+
+    // Job card
+    jcl.extend(job_card(&programs[0].name, "COBOL COMPILE", profile));
+
+    // Step 1: Compile each COBOL program
+    for index in 0..programs.len() {
+        compile_steps(&mut jcl, index, programs, profile);
+    }
+
+    // Step 2: Link-edit the main program with the programs it calls
+    let plan = plan_link(programs, profile);
+    jcl.push("//*".to_string());
+    jcl.push("//LKED     EXEC PGM=IEWL,PARM='LIST,XREF,LET',".to_string());
+    jcl.push("//             REGION=1024K".to_string());
+    jcl.push(format!("//SYSLIB   DD DSNAME={},DISP=SHR", profile.link_library));
+    if uses_sql {
+        // DSNHLI language interface
+        jcl.push(format!("//         DD DSNAME={},DISP=SHR", profile.db2_load_library));
+    }
+    if uses_cics {
+        // DFHELII EXEC interface stub
+        jcl.push(format!("//         DD DSNAME={},DISP=SHR", profile.cics_load_library));
+    }
+    for library in &plan.libraries {
+        // Autocall finds the called modules here
+        jcl.push(format!("//         DD DSNAME={},DISP=SHR", library));
+    }
+    jcl.push(format!("//SYSLIN   DD DSNAME={},DISP=(OLD,DELETE)", object_dataset(0, programs.len())));
+    if programs.len() > 1 {
+        // The called programs' object code is included by DD name, and the
+        // load module starts in the main program, not whichever subprogram
+        // the linkage editor happens to meet first
+        jcl.push("//         DD *".to_string());
+        for &index in &plan.includes {
+            jcl.push(format!(" INCLUDE OBJ{}", index + 1));
+        }
+        jcl.push(format!(" ENTRY {}", mvs_name(&programs[0].name)));
+        jcl.push("/*".to_string());
+        for index in 1..programs.len() {
+            jcl.push(format!("//OBJ{:<5} DD DSNAME={},DISP=(OLD,DELETE)", index + 1, object_dataset(index, programs.len())));
+        }
+    }
+    jcl.push("//SYSLMOD  DD DSNAME=&&GOSET(GO),DISP=(NEW,PASS),".to_string());
+    jcl.push("//            UNIT=SYSDA,SPACE=(CYL,(1,1,1))".to_string());
+    jcl.push("//SYSUT1   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push("//SYSPRINT DD SYSOUT=*".to_string());
+
+    // Step 3: Execute the program. CICS programs run under a CICS region and
+    // SQL programs need their DBRM bound into a plan first, so neither can
+    // simply be run at the end of the job.
+    jcl.push("//*".to_string());
+    if uses_sql || uses_cics {
+        jcl.push("//* GO STEP OMITTED: BIND AND INSTALL THE PROGRAM BEFORE RUNNING IT".to_string());
+        jcl.push("//".to_string());
+        return jcl;
+    }
+    jcl.push("//GO       EXEC PGM=*.LKED.SYSLMOD".to_string());
+    jcl.push(format!("//STEPLIB  DD DSNAME={},DISP=SHR", profile.runtime_library));
+    jcl.push("//SYSOUT   DD SYSOUT=*".to_string());
+    jcl.push("//SYSPRINT DD SYSOUT=*".to_string());
+    jcl.push("//SYSUDUMP DD SYSOUT=*".to_string());
+    jcl.push("//SYSIN    DD *".to_string());
+    jcl.push("//* INPUT DATA CARDS (IF ANY)".to_string());
+    jcl.push("/*".to_string());
+    jcl.push("//".to_string());
+
+    jcl
+}
+
+/// Options for the ANS COBOL compile step. Overriding PARM.COB replaces the
+/// procedure's own, so LOAD has to be asked for again or no object is written.
+fn ans_compile_options(program: &ProgramInfo) -> Vec<String> {
+    let mut options = program.compile_options.clone();
+    if !options.iter().any(|o| o == "LOAD" || o == "NOLOAD") {
+        options.insert(0, "LOAD".to_string());
+    }
+    options
+}
+
+/// Generate JCL for MVS 3.8j under Hercules, as set up by TK4-. A single
+/// program uses the COBUCLG compile, link and go procedure. Several are each
+/// compiled with COBUC and then link-edited together like on z/OS.
+pub fn generate_mvs38j_jcl(programs: &[ProgramInfo], profile: &JclProfile) -> Vec<String> {
+    let mut jcl = Vec::new();
+    let plan = plan_link(programs, profile);
+
+    jcl.extend(job_card(&programs[0].name, "COBOL COMPILE", profile));
+
+    if let [program] = programs {
+        jcl.push("//*".to_string());
+        jcl.push("//CLG      EXEC COBUCLG,".to_string());
+        jcl.extend(parm_cards("PARM.COB", &ans_compile_options(program)));
+        jcl.push("//COB.SYSIN DD *".to_string());
+        jcl.push(format!("//* {} {}", program.card_count, SOURCE_MARKER));
+        jcl.push("/*".to_string());
+        if !plan.libraries.is_empty() {
+            // Overriding the procedure's SYSLIB keeps COBLIB first and
+            // adds the call libraries after it
+            jcl.push(format!("//LKED.SYSLIB DD DSNAME={},DISP=SHR", profile.link_library));
+            for library in &plan.libraries {
+                jcl.push(format!("//         DD DSNAME={},DISP=SHR", library));
+            }
+        }
+        jcl.push(sysout("GO.SYSOUT", profile));
+        jcl.push("//GO.SYSIN DD *".to_string());
+        jcl.push("/*".to_string());
+        jcl.push("//".to_string());
+        return jcl;
+    }
+
+    // Compile each program into its own object dataset
+    for (index, program) in programs.iter().enumerate() {
+        jcl.push("//*".to_string());
+        jcl.push(format!("//{:<8} EXEC COBUC,", step_name("COMP", index, programs.len())));
+        jcl.extend(parm_cards("PARM.COB", &ans_compile_options(program)));
+        jcl.push(format!("//COB.SYSLIN DD DSNAME={},DISP=(MOD,PASS),", object_dataset(index, programs.len())));
+        jcl.push("//            UNIT=SYSDA,SPACE=(TRK,(10,10))".to_string());
+        jcl.push("//COB.SYSIN DD *".to_string());
+        jcl.push(format!("//* {} {}", program.card_count, SOURCE_MARKER));
+        jcl.push("/*".to_string());
+    }
+
+    // Link-edit the main program with the programs it calls
+    jcl.push("//*".to_string());
+    jcl.push("//LKED     EXEC PGM=IEWL,PARM='LIST,XREF,LET',".to_string());
+    jcl.push("//             REGION=256K,COND=(5,LT)".to_string());
+    jcl.push(format!("//SYSLIB   DD DSNAME={},DISP=SHR", profile.link_library));
+    for library in &plan.libraries {
+        jcl.push(format!("//         DD DSNAME={},DISP=SHR", library));
+    }
+    jcl.push(format!("//SYSLIN   DD DSNAME={},DISP=(OLD,DELETE)", object_dataset(0, programs.len())));
+    jcl.push("//         DD *".to_string());
+    for &index in &plan.includes {
+        jcl.push(format!(" INCLUDE OBJ{}", index + 1));
+    }
+    jcl.push(format!(" ENTRY {}", mvs_name(&programs[0].name)));
+    jcl.push("/*".to_string());
+    for index in 1..programs.len() {
+        jcl.push(format!("//OBJ{:<5} DD DSNAME={},DISP=(OLD,DELETE)", index + 1, object_dataset(index, programs.len())));
+    }
+    jcl.push("//SYSLMOD  DD DSNAME=&&GOSET(GO),DISP=(NEW,PASS),".to_string());
+    jcl.push("//            UNIT=SYSDA,SPACE=(CYL,(1,1,1))".to_string());
+    jcl.push("//SYSUT1   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
+    jcl.push(sysout("SYSPRINT", profile));
+
+    // Run it
+    jcl.push("//*".to_string());
+    jcl.push("//GO       EXEC PGM=*.LKED.SYSLMOD,COND=(5,LT)".to_string());
+    jcl.push(sysout("SYSOUT", profile));
+    jcl.push(sysout("SYSUDUMP", profile));
+    jcl.push("//SYSIN    DD *".to_string());
+    jcl.push("/*".to_string());
+    jcl.push("//".to_string());
+
+    jcl
+}
+
+/// Generate an IEBUPDTE job that adds each source deck to a partitioned
+/// dataset as a member named after its program. The library is named after
+/// the first program.
+pub fn generate_iebupdte_jcl(programs: &[ProgramInfo], profile: &JclProfile) -> Vec<String> {
+    let mut jcl = Vec::new();
+
+    jcl.extend(job_card(&programs[0].name, "LOAD SOURCE", profile));
+
+    // Single step: PARM=NEW takes every record from SYSIN and writes the
+    // members into the existing library on SYSUT2. DD DATA so that only
+    // the /* delimiter ends the in-stream deck.
+    jcl.push("//*".to_string());
+    jcl.push("//UPDATE   EXEC PGM=IEBUPDTE,PARM=NEW".to_string());
+    jcl.push(sysout("SYSPRINT", profile));
+    jcl.push(format!("//SYSUT2   DD DSNAME={},DISP=OLD", source_dataset(&programs[0].name, profile)));
+    jcl.push("//SYSIN    DD DATA".to_string());
+
+    // ADD refuses to overwrite an existing member. Sequence numbers are
+    // taken from columns 73-80 of the source cards as punched.
+    for program in programs {
+        jcl.push(format!("./        ADD   NAME={},LIST=ALL", mvs_name(&program.name)));
+        jcl.push(format!("//* {} {}", program.card_count, SOURCE_MARKER));
+    }
+    jcl.push("./        ENDUP".to_string());
+    jcl.push("/*".to_string());
+    jcl.push("//".to_string());
+
+    jcl
+}
+
+/// Generate an IEBGENER job that copies each source deck into a new
+/// sequential dataset named after its program
+pub fn generate_iebgener_jcl(programs: &[ProgramInfo], profile: &JclProfile) -> Vec<String> {
+    let mut jcl = Vec::new();
+
+    jcl.extend(job_card(&programs[0].name, "COPY SOURCE", profile));
+
+    for (index, program) in programs.iter().enumerate() {
+        jcl.push("//*".to_string());
+        jcl.push(format!("//{:<8} EXEC PGM=IEBGENER", step_name("COPY", index, programs.len())));
+        jcl.push(sysout("SYSPRINT", profile));
+        jcl.push("//SYSIN    DD DUMMY".to_string());
+        jcl.push(format!("//SYSUT2   DD DSNAME={},", source_dataset(&program.name, profile)));
+        jcl.push("//            DISP=(NEW,CATLG,DELETE),UNIT=SYSDA,".to_string());
+        jcl.push("//            SPACE=(TRK,(5,5),RLSE),".to_string());
+        jcl.push("//            DCB=(RECFM=FB,LRECL=80,BLKSIZE=3120)".to_string());
+        jcl.push("//SYSUT1   DD DATA".to_string());
+
+        jcl.push(format!("//* {} {}", program.card_count, SOURCE_MARKER));
+        jcl.push("/*".to_string());
+    }
+    jcl.push("//".to_string());
+
+    jcl
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mvs_name_follows_the_compiler() {
+        assert_eq!(mvs_name("hello-world"), "HELLO0WO");
+        assert_eq!(mvs_name("PAYROLL"), "PAYROLL");
+        assert_eq!(mvs_name("1ST-PASS"), "AST0PASS");
+        assert_eq!(mvs_name("0-AND-1"), "J0AND01");
+        assert_eq!(mvs_name("..."), "COBPROG");
+    }
+
+    fn program(name: &str, calls: &[&str]) -> ProgramInfo {
+        ProgramInfo {
+            name: name.to_string(),
+            card_count: 1,
+            compile_options: Vec::new(),
+            uses_sql: false,
+            uses_cics: false,
+            calls: calls.iter().map(|call| call.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn unresolved_calls_stop_the_deck() {
+        let mut profile = Profile::Default.settings(Dialect::Zos);
+        let programs = [program("MAIN", &["SUB-ONE", "MISSING"]), program("SUB-ONE", &[])];
+        let err = check_calls(&programs, &profile, JobLayout::Combined).unwrap_err();
+        assert!(err.contains("MAIN -> MISSING"), "{}", err);
+        assert!(!err.contains("SUB-ONE"), "{}", err);
+
+        profile.allow_unresolved_calls = true;
+        let warnings = check_calls(&programs, &profile, JobLayout::Combined).unwrap();
+        assert_eq!(warnings, ["unresolved call MAIN -> MISSING"]);
+    }
+
+    #[test]
+    fn iebupdte_adds_a_member_for_each_program() {
+        let profile = Profile::Default.settings(Dialect::Zos);
+        let programs = [program("PAYROLL", &[]), program("TAXCALC", &[])];
+        let jcl = generate_iebupdte_jcl(&programs, &profile);
+        assert!(jcl.contains(&"//UPDATE   EXEC PGM=IEBUPDTE,PARM=NEW".to_string()));
+        assert!(jcl.contains(&format!("//SYSUT2   DD DSNAME={},DISP=OLD", source_dataset("PAYROLL", &profile))));
+        let adds: Vec<&String> = jcl.iter().filter(|card| card.starts_with("./")).collect();
+        assert_eq!(adds, ["./        ADD   NAME=PAYROLL,LIST=ALL", "./        ADD   NAME=TAXCALC,LIST=ALL", "./        ENDUP"]);
+        // Each member's source follows its ADD card, numbered in tens
+        let add = jcl.iter().position(|card| card.contains("NAME=PAYROLL")).unwrap();
+        assert_eq!(jcl[add + 1], format!("//* 1 {}", SOURCE_MARKER));
+        assert_eq!(JclKind::Iebupdte.sequence_increment(), 10);
+        assert_eq!(JclKind::Iebgener.sequence_increment(), 1);
+    }
+
+    #[test]
+    fn iebgener_copies_each_program_to_its_own_dataset() {
+        let profile = Profile::Default.settings(Dialect::Zos);
+        let programs = [program("PAYROLL", &[]), program("TAXCALC", &[])];
+        let jcl = generate_iebgener_jcl(&programs, &profile);
+        for program in ["PAYROLL", "TAXCALC"] {
+            let sysut2 = jcl
+                .iter()
+                .position(|card| *card == format!("//SYSUT2   DD DSNAME={},", source_dataset(program, &profile)))
+                .unwrap();
+            assert_eq!(jcl[sysut2 + 1], "//            DISP=(NEW,CATLG,DELETE),UNIT=SYSDA,");
+            // SYSUT1 is the in-stream source, ended by /*
+            assert_eq!(jcl[sysut2 + 4], "//SYSUT1   DD DATA");
+            assert_eq!(jcl[sysut2 + 5], format!("//* 1 {}", SOURCE_MARKER));
+            assert_eq!(jcl[sysut2 + 6], "/*");
+        }
+        assert_eq!(jcl.iter().filter(|card| card.contains("EXEC PGM=IEBGENER")).count(), 2);
+        assert_eq!(jcl.last().unwrap(), "//");
+    }
+}
@@ -0,0 +1,333 @@
+// A keypunch at the terminal, for learning the IBM 029 routine: each key
+// punches the next column of the card in the punch station, and the card
+// that went before waits at the read station for the DUP key to copy from.
+// As on the machine, backspacing moves the card back without filling any
+// holes, so a mistake is put right by duplicating the card up to it.
+//
+//   any character  punch it and space on
+//   Space          leave the column blank
+//   Backspace      move back one column
+//   Ctrl+D         DUP: copy this column from the previous card
+//   Ctrl+P         MULT PCH: hold the column while punches are added
+//   Enter          REL then FEED: release the card and feed a blank one
+//   Esc            release the card and stop
+//   Ctrl+C         stop, leaving the card in the punch station out
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::{cursor, execute, queue, terminal};
+
+use crate::{PunchCard, COLUMNS, ROWS};
+
+const ROW_NAMES: [&str; ROWS] = ["12", "11", " 0", " 1", " 2", " 3", " 4", " 5", " 6", " 7", " 8", " 9"];
+
+/// Terminal columns the card needs: row names, a border each side and the
+/// 80 columns
+const CARD_WIDTH: u16 = COLUMNS as u16 + 5;
+
+/// The machine's state between keystrokes
+struct Keypunch<'a> {
+    /// Cards released to the stacker, the last one at the read station
+    stacker: Vec<PunchCard>,
+    /// Card in the punch station
+    card: PunchCard,
+    /// Column under the punch, 0-based
+    column: usize,
+    multi_punch: bool,
+    message: String,
+    encoding_map: &'a HashMap<char, Vec<usize>>,
+    decoding_map: &'a HashMap<Vec<usize>, char>,
+}
+
+impl Keypunch<'_> {
+    /// Move on a column, releasing the card when it runs out
+    fn space(&mut self) {
+        if self.multi_punch {
+            return;
+        }
+        self.column += 1;
+        if self.column == COLUMNS {
+            self.release();
+            self.message = "Column 80 passed: card released".to_string();
+        }
+    }
+
+    /// Add punches to the column under the punch
+    fn punch(&mut self, punches: &[usize]) {
+        let column = &mut self.card.columns[self.column];
+        column.extend_from_slice(punches);
+        column.sort();
+        column.dedup();
+        self.space();
+    }
+
+    fn release(&mut self) {
+        let card = std::mem::replace(&mut self.card, PunchCard::new());
+        self.stacker.push(card);
+        self.column = 0;
+        self.multi_punch = false;
+    }
+
+    /// Carry out one key; false once the session is over
+    fn key(&mut self, key: KeyEvent) -> bool {
+        self.message.clear();
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {
+                if self.card.columns.iter().any(|punches| !punches.is_empty()) {
+                    self.release();
+                }
+                return false;
+            }
+            KeyCode::Char('c') if control => return false,
+            KeyCode::Char('d') if control => match self.stacker.last() {
+                Some(previous) => {
+                    let punches = previous.columns[self.column].clone();
+                    self.punch(&punches);
+                }
+                None => self.message = "No card at the read station to duplicate from".to_string(),
+            },
+            KeyCode::Char('p') if control => {
+                self.multi_punch = !self.multi_punch;
+                // Letting go of MULT PCH moves on past the column just built
+                if !self.multi_punch {
+                    self.space();
+                }
+            }
+            KeyCode::Enter => {
+                self.release();
+                self.message = format!("Card {} released, new card fed", self.stacker.len());
+            }
+            KeyCode::Backspace if self.column > 0 => self.column -= 1,
+            KeyCode::Char(' ') if !self.multi_punch => self.space(),
+            KeyCode::Char(ch) if !control => {
+                let uppercase_ch = ch.to_uppercase().next().unwrap_or(ch);
+                match self.encoding_map.get(&uppercase_ch) {
+                    Some(punches) => {
+                        let punches = punches.clone();
+                        self.punch(&punches);
+                    }
+                    None => self.message = format!("'{}' is not on the keyboard", ch),
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// What the 029 prints along the top edge for a column
+    fn printed(&self, punches: &[usize]) -> char {
+        match self.decoding_map.get(punches) {
+            Some(&ch) => ch,
+            None => '▒',
+        }
+    }
+
+    fn draw<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        queue!(out, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+        let (width, _) = terminal::size()?;
+        if width < CARD_WIDTH {
+            queue!(out, Print(format!("Widen the terminal to {} columns to see the whole card", CARD_WIDTH)))?;
+            return out.flush();
+        }
+
+        let mode = if self.multi_punch { "  [MULT PCH]" } else { "" };
+        let lines = [
+            format!("IBM 029 keypunch   card {:<5} column {:>2}{}", self.stacker.len() + 1, self.column + 1, mode),
+            String::new(),
+            format!("    {}", self.card.columns.iter().map(|p| self.printed(p)).collect::<String>()),
+            format!("   ┌{}┐", "─".repeat(COLUMNS)),
+        ];
+        for (line_idx, line) in lines.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, line_idx as u16), Print(line))?;
+        }
+
+        // Holes show solid; unpunched positions show the row's printed digit
+        for (row, row_name) in ROW_NAMES.iter().enumerate() {
+            let y = lines.len() as u16 + row as u16;
+            queue!(out, cursor::MoveTo(0, y), Print(format!("{} │", row_name)))?;
+            for (col_idx, punches) in self.card.columns.iter().enumerate() {
+                let mark = if punches.contains(&row) {
+                    "█".to_string()
+                } else if row >= 2 {
+                    row_name.trim().to_string()
+                } else {
+                    " ".to_string()
+                };
+                if col_idx == self.column {
+                    queue!(out, SetAttribute(Attribute::Reverse), Print(mark), SetAttribute(Attribute::Reset))?;
+                } else {
+                    queue!(out, Print(mark))?;
+                }
+            }
+            queue!(out, Print("│"))?;
+        }
+
+        let bottom = lines.len() as u16 + ROWS as u16;
+        let previous = self.stacker.last().map_or_else(
+            || "(none)".to_string(),
+            |card| card.columns.iter().map(|p| self.printed(p)).collect::<String>().trim_end().to_string(),
+        );
+        let footer = [
+            format!("   └{}┘", "─".repeat(COLUMNS)),
+            format!("    {:>width$}", "▲", width = self.column + 1),
+            format!("Read station: {}", previous),
+            String::new(),
+            "Keys: type to punch  Space skip  Backspace back  Ctrl+D dup  Ctrl+P mult pch  Enter release/feed  Esc finish"
+                .to_string(),
+            self.message.clone(),
+        ];
+        for (line_idx, line) in footer.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, bottom + line_idx as u16), Print(line))?;
+        }
+        out.flush()
+    }
+}
+
+/// Puts the terminal back however the session ends
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Run a keypunch session, carrying on after the given cards, and return
+/// the whole deck once the operator finishes
+pub fn run(
+    cards: Vec<PunchCard>,
+    encoding_map: &HashMap<char, Vec<usize>>,
+    decoding_map: &HashMap<Vec<usize>, char>,
+) -> io::Result<Vec<PunchCard>> {
+    let mut keypunch = Keypunch {
+        stacker: cards,
+        card: PunchCard::new(),
+        column: 0,
+        multi_punch: false,
+        message: String::new(),
+        encoding_map,
+        decoding_map,
+    };
+
+    let _terminal = RawTerminal::enter()?;
+    let mut out = io::stdout();
+    loop {
+        keypunch.draw(&mut out)?;
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press && !keypunch.key(key) => break,
+            _ => {}
+        }
+    }
+
+    Ok(keypunch.stacker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_hollerith_decoding, get_hollerith_encoding};
+
+    fn press(keypunch: &mut Keypunch, code: KeyCode) -> bool {
+        keypunch.key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn control(keypunch: &mut Keypunch, ch: char) -> bool {
+        keypunch.key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::CONTROL))
+    }
+
+    fn type_text(keypunch: &mut Keypunch, text: &str) {
+        for ch in text.chars() {
+            assert!(press(keypunch, KeyCode::Char(ch)));
+        }
+    }
+
+    /// The characters punched on a card, blank columns trimmed off the end
+    fn text(keypunch: &Keypunch, card: &PunchCard) -> String {
+        card.columns.iter().map(|punches| keypunch.printed(punches)).collect::<String>().trim_end().to_string()
+    }
+
+    #[test]
+    fn keys_punch_columns_and_feed_cards() {
+        let (encoding_map, decoding_map) = (get_hollerith_encoding(), get_hollerith_decoding());
+        let mut keypunch = Keypunch {
+            stacker: Vec::new(),
+            card: PunchCard::new(),
+            column: 0,
+            multi_punch: false,
+            message: String::new(),
+            encoding_map: &encoding_map,
+            decoding_map: &decoding_map,
+        };
+
+        type_text(&mut keypunch, "move a");
+        assert_eq!(keypunch.column, 6);
+        assert_eq!(text(&keypunch, &keypunch.card), "MOVE A");
+
+        // Backspacing leaves the holes; punching again adds to them
+        press(&mut keypunch, KeyCode::Backspace);
+        assert_eq!(keypunch.column, 5);
+        assert_eq!(keypunch.card.columns[5], encoding_map[&'A']);
+
+        press(&mut keypunch, KeyCode::Enter);
+        assert_eq!(keypunch.stacker.len(), 1);
+        assert_eq!(keypunch.column, 0);
+        assert!(keypunch.card.columns.iter().all(Vec::is_empty));
+
+        // DUP copies from the card at the read station
+        for _ in 0..5 {
+            control(&mut keypunch, 'd');
+        }
+        type_text(&mut keypunch, "B");
+        assert_eq!(text(&keypunch, &keypunch.card), "MOVE B");
+
+        // MULT PCH holds the column until it is let go
+        press(&mut keypunch, KeyCode::Char(' '));
+        control(&mut keypunch, 'p');
+        type_text(&mut keypunch, "3");
+        type_text(&mut keypunch, "8");
+        assert_eq!(keypunch.column, 7);
+        control(&mut keypunch, 'p');
+        assert_eq!(keypunch.column, 8);
+        assert_eq!(keypunch.card.columns[7], encoding_map[&'#']);
+
+        assert!(!press(&mut keypunch, KeyCode::Esc));
+        assert_eq!(keypunch.stacker.len(), 2);
+        assert_eq!(text(&keypunch, &keypunch.stacker[1]), "MOVE B #");
+    }
+
+    #[test]
+    fn column_80_releases_the_card() {
+        let (encoding_map, decoding_map) = (get_hollerith_encoding(), get_hollerith_decoding());
+        let mut keypunch = Keypunch {
+            stacker: vec![PunchCard::new()],
+            card: PunchCard::new(),
+            column: 0,
+            multi_punch: false,
+            message: String::new(),
+            encoding_map: &encoding_map,
+            decoding_map: &decoding_map,
+        };
+
+        type_text(&mut keypunch, &"X".repeat(COLUMNS));
+        assert_eq!(keypunch.stacker.len(), 2);
+        assert_eq!(keypunch.column, 0);
+        assert_eq!(text(&keypunch, &keypunch.stacker[1]), "X".repeat(COLUMNS));
+
+        // A blank card is not stacked when the session ends
+        assert!(!press(&mut keypunch, KeyCode::Esc));
+        assert_eq!(keypunch.stacker.len(), 2);
+    }
+}
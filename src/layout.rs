@@ -0,0 +1,285 @@
+// Imposition: where the cards go on the printed page. Cards are set in a
+// grid filled left to right and top to bottom, several to a page, or one
+// to a page the exact size of a card for printers that take real card
+// stock. Cards may be turned a quarter turn anticlockwise to fit the page
+// better. Without a gutter the space left over is shared out equally
+// around and between the cards.
+//
+// For cutting the sheets apart with a guillotine, each card may have a
+// bleed of card colour beyond its edges and crop marks outside that. Cut
+// and stack order spreads the deck over the sheets so that once the stack
+// is cut, the piles put one on another are in deck order: the first pile
+// holds the first card of every sheet, and so on.
+
+use std::str::FromStr;
+
+use crate::{CARD_HEIGHT_MM, CARD_WIDTH_MM, PT_PER_MM};
+
+const MM_PER_INCH: f32 = 25.4;
+
+/// Crop marks start this far beyond the bleed, and are this long
+const CROP_MARK_OFFSET_MM: f32 = 1.0;
+const CROP_MARK_LENGTH_MM: f32 = 5.0;
+
+/// Paper size in millimetres, portrait
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageSize {
+    pub width_mm: f32,
+    pub height_mm: f32,
+}
+
+impl PageSize {
+    pub const A4: PageSize = PageSize { width_mm: 210.0, height_mm: 297.0 };
+    pub const CARD: PageSize = PageSize { width_mm: CARD_WIDTH_MM, height_mm: CARD_HEIGHT_MM };
+}
+
+impl FromStr for PageSize {
+    type Err = String;
+
+    /// `a4`, `a3`, `a5`, `letter`, `legal`, `tabloid`, `card`, or a custom
+    /// `WIDTHxHEIGHT` in millimetres, or in inches with `in` after it, e.g.
+    /// `8.5x11in`
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let inches = |width: f32, height: f32| PageSize { width_mm: width * MM_PER_INCH, height_mm: height * MM_PER_INCH };
+        let size = match text.to_ascii_lowercase().as_str() {
+            "a3" => PageSize { width_mm: 297.0, height_mm: 420.0 },
+            "a4" => PageSize::A4,
+            "a5" => PageSize { width_mm: 148.0, height_mm: 210.0 },
+            "letter" => inches(8.5, 11.0),
+            "legal" => inches(8.5, 14.0),
+            "tabloid" => inches(11.0, 17.0),
+            "card" => PageSize::CARD,
+            custom => {
+                let (dimensions, scale) = match custom.strip_suffix("in") {
+                    Some(dimensions) => (dimensions, MM_PER_INCH),
+                    None => (custom.strip_suffix("mm").unwrap_or(custom), 1.0),
+                };
+                let (width, height) = dimensions
+                    .split_once('x')
+                    .ok_or_else(|| format!("unknown page size {}; use a name such as a4 or letter, or WIDTHxHEIGHT", text))?;
+                let parse = |n: &str| {
+                    n.trim()
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|n| n.is_finite() && *n > 0.0)
+                        .ok_or_else(|| format!("bad page dimension {}", n))
+                };
+                PageSize { width_mm: parse(width)? * scale, height_mm: parse(height)? * scale }
+            }
+        };
+        Ok(size)
+    }
+}
+
+/// How the cards are set on the page
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub page_width: f32,
+    pub page_height: f32,
+    /// Size of one card, in points
+    pub card_width: f32,
+    pub card_height: f32,
+    pub cards_per_page: usize,
+    /// Whether the cards are turned a quarter turn anticlockwise
+    pub rotated: bool,
+    /// Card colour printed beyond each edge of the card, in points
+    pub bleed: f32,
+    /// Spread the deck over the sheets for cutting and stacking, rather
+    /// than filling each page in turn
+    pub cut_and_stack: bool,
+    /// Bottom-left corner of the space each card and its bleed take, by its
+    /// position on the page
+    origins: Vec<(f32, f32)>,
+}
+
+impl Default for Layout {
+    /// Three cards down an A4 page
+    fn default() -> Self {
+        Layout::new(PageSize::A4, None, None, 0.0, false).expect("cards fit on A4")
+    }
+}
+
+impl Layout {
+    /// Work out the grid of cards for a page. `cards_per_page` defaults to as
+    /// many as fit; `gutter_mm` is the gap between cards' bleeds, and without
+    /// it the spare space is shared out equally.
+    pub fn new(
+        page: PageSize,
+        cards_per_page: Option<usize>,
+        gutter_mm: Option<f32>,
+        bleed_mm: f32,
+        rotated: bool,
+    ) -> Result<Layout, String> {
+        let (page_width, page_height) = (page.width_mm * PT_PER_MM, page.height_mm * PT_PER_MM);
+        let (card_width, card_height) = (CARD_WIDTH_MM * PT_PER_MM, CARD_HEIGHT_MM * PT_PER_MM);
+        let bleed = bleed_mm * PT_PER_MM;
+        // The space a card and its bleed take on the page, turned or not
+        let (space_width, space_height) = if rotated { (card_height, card_width) } else { (card_width, card_height) };
+        let (space_width, space_height) = (space_width + 2.0 * bleed, space_height + 2.0 * bleed);
+
+        // Allow for rounding in the page sizes, so a card-sized page fits
+        // its card
+        let slack = 0.01;
+        let gutter = gutter_mm.unwrap_or(0.0) * PT_PER_MM;
+        let fit = |page: f32, card: f32| ((page + gutter + slack) / (card + gutter)).floor() as usize;
+        let (most_across, most_down) = (fit(page_width, space_width), fit(page_height, space_height));
+        let capacity = most_across * most_down;
+        if capacity == 0 {
+            return Err(format!(
+                "a {:.1} x {:.1} mm card does not fit on a {:.1} x {:.1} mm page",
+                space_width / PT_PER_MM,
+                space_height / PT_PER_MM,
+                page.width_mm,
+                page.height_mm
+            ));
+        }
+        let cards_per_page = cards_per_page.unwrap_or(capacity);
+        if cards_per_page == 0 || cards_per_page > capacity {
+            return Err(format!(
+                "{} cards do not fit on the page; at most {} do ({} across, {} down){}",
+                cards_per_page,
+                capacity,
+                most_across,
+                most_down,
+                if rotated { "" } else { ", or try turning them" }
+            ));
+        }
+
+        let across = most_across.min(cards_per_page);
+        let down = cards_per_page.div_ceil(across);
+        // The gap between cards, and the margin before the first
+        let spread = |page: f32, space: f32, count: usize| match gutter_mm {
+            Some(_) => (gutter, (page - count as f32 * space - (count - 1) as f32 * gutter) / 2.0),
+            None => {
+                let gap = (page - count as f32 * space) / (count as f32 + 1.0);
+                (gap, gap)
+            }
+        };
+        let (gap_x, margin_x) = spread(page_width, space_width, across);
+        let (gap_y, margin_y) = spread(page_height, space_height, down);
+
+        let origins = (0..cards_per_page)
+            .map(|position| {
+                let (column, row) = (position % across, position / across);
+                let x = margin_x + column as f32 * (space_width + gap_x);
+                // Y counts from the bottom in PDF coordinates
+                let y = page_height - margin_y - (row as f32 + 1.0) * space_height - row as f32 * gap_y;
+                (x, y)
+            })
+            .collect();
+        Ok(Layout {
+            page_width,
+            page_height,
+            card_width,
+            card_height,
+            cards_per_page,
+            rotated,
+            bleed,
+            cut_and_stack: false,
+            origins,
+        })
+    }
+
+    /// Where a card, trimmed, lies on the page: x, y, width, height
+    pub fn card_space(&self, position: usize) -> (f32, f32, f32, f32) {
+        let (x, y) = self.origins[position];
+        let (x, y) = (x + self.bleed, y + self.bleed);
+        if self.rotated {
+            (x, y, self.card_height, self.card_width)
+        } else {
+            (x, y, self.card_width, self.card_height)
+        }
+    }
+
+    /// Matrix taking a card drawn upright with its bottom-left corner at the
+    /// origin to its place on the page
+    pub fn card_transform(&self, position: usize) -> [f32; 6] {
+        let (x, y, width, _) = self.card_space(position);
+        if self.rotated {
+            [0.0, 1.0, -1.0, 0.0, x + width, y]
+        } else {
+            [1.0, 0.0, 0.0, 1.0, x, y]
+        }
+    }
+
+    /// The cards on each page, by position, as indices into a deck of
+    /// `count` cards. In cut and stack order the last sheets may have gaps
+    /// at their last positions.
+    pub fn impose(&self, count: usize) -> Vec<Vec<usize>> {
+        let sheets = count.div_ceil(self.cards_per_page);
+        (0..sheets)
+            .map(|sheet| {
+                (0..self.cards_per_page)
+                    .map(|position| match self.cut_and_stack {
+                        true => cut_and_stack_index(sheets, sheet, position),
+                        false => sheet * self.cards_per_page + position,
+                    })
+                    .filter(|&card_idx| card_idx < count)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Crop mark lines for the cards at the given positions, in page
+    /// points. Marks that would run onto another card or its bleed are left
+    /// out; the ones along the outside still line up with every cut.
+    pub fn crop_marks(&self, positions: usize) -> Vec<((f32, f32), (f32, f32))> {
+        let offset = self.bleed + CROP_MARK_OFFSET_MM * PT_PER_MM;
+        let length = CROP_MARK_LENGTH_MM * PT_PER_MM;
+        let spaces: Vec<(f32, f32, f32, f32)> = (0..positions)
+            .map(|position| {
+                let (x, y, width, height) = self.card_space(position);
+                (x - self.bleed, y - self.bleed, x + width + self.bleed, y + height + self.bleed)
+            })
+            .collect();
+        let overlaps = |((x1, y1), (x2, y2)): ((f32, f32), (f32, f32))| {
+            spaces.iter().any(|&(left, bottom, right, top)| {
+                x1.max(x2) > left && x1.min(x2) < right && y1.max(y2) > bottom && y1.min(y2) < top
+            })
+        };
+
+        let mut marks = Vec::new();
+        for position in 0..positions {
+            let (x, y, width, height) = self.card_space(position);
+            for (corner_x, corner_y, out_x, out_y) in
+                [(x, y, -1.0, -1.0), (x + width, y, 1.0, -1.0), (x, y + height, -1.0, 1.0), (x + width, y + height, 1.0, 1.0)]
+            {
+                // One mark along each edge through the corner, pointing away
+                let along_x = ((corner_x + out_x * offset, corner_y), (corner_x + out_x * (offset + length), corner_y));
+                let along_y = ((corner_x, corner_y + out_y * offset), (corner_x, corner_y + out_y * (offset + length)));
+                marks.extend([along_x, along_y].into_iter().filter(|&mark| !overlaps(mark)));
+            }
+        }
+        marks
+    }
+}
+
+/// Deck index of the card at a position on a sheet, in cut and stack order
+/// over the given number of sheets
+pub fn cut_and_stack_index(sheets: usize, sheet: usize, position: usize) -> usize {
+    position * sheets + sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(text: &str) -> (f32, f32) {
+        let size: PageSize = text.parse().unwrap();
+        (size.width_mm, size.height_mm)
+    }
+
+    #[test]
+    fn page_sizes_parse() {
+        assert_eq!(size("A4"), (210.0, 297.0));
+        assert_eq!(size("a5"), (148.0, 210.0));
+        assert_eq!(size("letter"), (215.9, 279.4));
+        assert_eq!(size("card"), (CARD_WIDTH_MM, CARD_HEIGHT_MM));
+        assert_eq!(size("100x150"), (100.0, 150.0));
+        assert_eq!(size("100x150mm"), (100.0, 150.0));
+        assert_eq!(size("4x6in"), (101.6, 152.4));
+        for text in ["b5", "x", "100x", "0x150", "-100x150", "100x150cm", "infx150"] {
+            assert!(text.parse::<PageSize>().is_err(), "{} parsed", text);
+        }
+    }
+}
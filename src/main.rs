@@ -1,33 +1,62 @@
 use std::collections::HashMap;
 use std::fs;
 use std::io::{self, BufRead};
-use clap::Parser;
+use std::time::Duration;
+use clap::{Parser, Subcommand};
+
+mod binary_deck;
+mod calibration;
+mod card_image;
+mod cobol68;
+mod compiler_options;
+mod cutter;
+mod drum_card;
+mod ebcdic;
+mod interpret;
+mod jcl;
+mod keypunch;
+mod layout;
+mod object_deck;
+mod printer;
+mod render;
+mod scan;
+mod sockdev;
+mod vector_card;
+mod verify;
+
+use calibration::Calibration;
+use card_image::CardImageFormat;
+use cutter::{CutFormat, CutOptions};
+use interpret::InterpreterLine;
+use jcl::{CallLibrary, Dialect, JclKind, JclProfile, JobLayout, Profile, ProgramInfo};
+use layout::{Layout, PageSize};
+use printer::PrinterProfile;
+use sockdev::{ReaderCode, ReaderConnection};
 
 // IBM punch card dimensions in mm
 const CARD_WIDTH_MM: f32 = 187.325;
 const CARD_HEIGHT_MM: f32 = 82.55;
 
-// A4 dimensions in mm
-const A4_WIDTH_MM: f32 = 210.0;
-const A4_HEIGHT_MM: f32 = 297.0;
-
 // Punch card has 80 columns and 12 rows
 const COLUMNS: usize = 80;
 const ROWS: usize = 12;
 
-// Cards per page
-const CARDS_PER_PAGE: usize = 3;
-
 // Points per mm
 const PT_PER_MM: f32 = 2.834645;
 
-// Template punch hole positions (in pixels from template image)
-const FIRST_PUNCH_X: f32 = 30.0;  // X position of first column
-const FIRST_PUNCH_Y: f32 = 25.0;  // Y position of first row (12-punch)
-const COLUMN_SPACING: f32 = 9.0; // Pixels between columns
-const ROW_SPACING: f32 = 27.0;    // Pixels between rows
-const PUNCH_WIDTH_PX: f32 = 7.0;  // Punch width in pixels
-const PUNCH_HEIGHT_PX: f32 = 15.0; // Punch height in pixels
+// Template punch hole positions (in pixels from template image); the
+// bundled template's calibration, as measured by `calibrate`, for
+// templates without their own
+const FIRST_PUNCH_X: f32 = 28.5;     // X position of first column
+const FIRST_PUNCH_Y: f32 = 27.0;     // Y position of first row (12-punch)
+const COLUMN_SPACING: f32 = 9.327;   // Pixels between columns
+const ROW_SPACING: f32 = 26.667;     // Pixels between rows
+const PUNCH_WIDTH_PX: f32 = 5.9;     // Punch width in pixels
+const PUNCH_HEIGHT_PX: f32 = 13.33;  // Punch height in pixels
+
+// Verifier notches, also in template pixels, measured from the image edge
+const NOTCH_DEPTH_PX: f32 = 14.0;
+const NOTCH_HALF_WIDTH_PX: f32 = 5.0;
 
 fn get_hollerith_encoding() -> HashMap<char, Vec<usize>> {
     let mut map = HashMap::new();
@@ -53,26 +82,266 @@ fn get_hollerith_encoding() -> HashMap<char, Vec<usize>> {
         map.insert(digit, vec![i as usize + 2]);
     }
     
-    // Special characters (simplified set)
-    map.insert(' ', vec![]);  // No punches for space
-    map.insert('.', vec![0, 1, 10]);  // 12-11-8
-    map.insert(',', vec![0, 5]);      // 12-3
-    map.insert('(', vec![0, 7]);      // 12-5
-    map.insert(')', vec![1, 7]);      // 11-5
-    map.insert('+', vec![0, 8]);      // 12-6
+    // Special characters, with their IBM 029 keypunch codes. Each code
+    // belongs to one character so punched cards can be read back.
+    map.insert(' ', vec![]);          // No punches for space
+    map.insert('&', vec![0]);         // 12
     map.insert('-', vec![1]);         // 11
-    map.insert('*', vec![1, 6]);      // 11-4
     map.insert('/', vec![2, 3]);      // 0-1
-    map.insert('=', vec![0, 8]);      // 12-6 (same as +)
-    map.insert('$', vec![1, 5]);      // 11-3
-    map.insert('\'', vec![0, 10]);    // 12-8
-    map.insert(':', vec![4, 10]);     // 2-8
-    map.insert(';', vec![0, 1, 8]);   // 12-11-6
-    map.insert('"', vec![0, 10]);     // 12-8
+    map.insert('.', vec![0, 5, 10]);  // 12-8-3
+    map.insert('<', vec![0, 6, 10]);  // 12-8-4
+    map.insert('(', vec![0, 7, 10]);  // 12-8-5
+    map.insert('+', vec![0, 8, 10]);  // 12-8-6
+    map.insert('|', vec![0, 9, 10]);  // 12-8-7
+    map.insert('!', vec![1, 4, 10]);  // 11-8-2
+    map.insert('$', vec![1, 5, 10]);  // 11-8-3
+    map.insert('*', vec![1, 6, 10]);  // 11-8-4
+    map.insert(')', vec![1, 7, 10]);  // 11-8-5
+    map.insert(';', vec![1, 8, 10]);  // 11-8-6
+    map.insert(',', vec![2, 5, 10]);  // 0-8-3
+    map.insert('%', vec![2, 6, 10]);  // 0-8-4
+    map.insert('_', vec![2, 7, 10]);  // 0-8-5
+    map.insert('>', vec![2, 8, 10]);  // 0-8-6
+    map.insert('?', vec![2, 9, 10]);  // 0-8-7
+    map.insert(':', vec![4, 10]);     // 8-2
+    map.insert('#', vec![5, 10]);     // 8-3
+    map.insert('@', vec![6, 10]);     // 8-4
+    map.insert('\'', vec![7, 10]);    // 8-5
+    map.insert('=', vec![8, 10]);     // 8-6
+    map.insert('"', vec![9, 10]);     // 8-7
     
     map
 }
 
+/// Inverse of the Hollerith table, for reading punches back as characters
+fn get_hollerith_decoding() -> HashMap<Vec<usize>, char> {
+    get_hollerith_encoding()
+        .into_iter()
+        .map(|(ch, punches)| (punches, ch))
+        .collect()
+}
+
+/// Written form of a column's punches, e.g. 12-0-8-2
+fn punch_pattern(punches: &[usize]) -> String {
+    const ROW_NAMES: [&str; ROWS] = ["12", "11", "0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+    // Zones first, then 9 and 8 ahead of the digit, as in 12-0-9-8-1
+    let mut rows = punches.to_vec();
+    rows.sort_by_key(|&row| match row {
+        0..=2 => row,
+        11 => 3,
+        10 => 4,
+        _ => row + 2,
+    });
+    rows.iter().map(|&row| ROW_NAMES[row]).collect::<Vec<_>>().join("-")
+}
+
+/// Rows named in a written punch pattern such as 12-0-8-2; the reverse of
+/// `punch_pattern`
+fn parse_punch_pattern(pattern: &str) -> Option<Vec<usize>> {
+    let mut rows = Vec::new();
+    for name in pattern.split('-') {
+        let row = match name {
+            "12" => 0,
+            "11" => 1,
+            _ => match name.parse::<usize>().ok()? {
+                digit @ 0..=9 => digit + 2,
+                _ => return None,
+            },
+        };
+        rows.push(row);
+    }
+    rows.sort();
+    Some(rows)
+}
+
+/// Shown in place of a column whose punches are no character in the table
+/// (the Unicode replacement character, which no card can carry)
+const UNMAPPED_COLUMN: char = '\u{FFFD}';
+
+/// Lay a source line out into the 80 card columns
+fn format_cobol_line(line: &str, sequence_num: usize) -> String {
+    // Format the line with proper COBOL columns:
+    // Columns 1-6: Sequence number (right-aligned, zero-padded)
+    // Column 7: Indicator area (preserved from input or space)
+    // Columns 8-72: COBOL code
+    // Columns 73-80: Sequence/identification (card sequence number)
+    
+    let sequence_str = format!("{:06}", sequence_num % 1000000);
+    let card_seq_str = format!("{:08}", sequence_num);
+    
+    // Check if line starts with spaces (typical COBOL indentation)
+    // If it starts with 7+ spaces, it's likely already formatted
+    let starts_with_spaces = line.starts_with("       "); // 7 spaces
+    
+    let (indicator, code_part) = if starts_with_spaces && line.len() > 7 {
+        // Line has leading spaces - treat as formatted
+        // Extract indicator from position 6 and code from position 7
+        let indicator = line.chars().nth(6).unwrap_or(' ');
+        let code = line[7..].trim_end();
+        (indicator, code.to_string())
+    } else {
+        // No leading spaces or too short - treat entire line as code
+        (' ', line.trim().to_string())
+    };
+    
+    // Build the full 80-column line
+    let mut formatted = String::with_capacity(80);
+    formatted.push_str(&sequence_str);           // Columns 1-6
+    formatted.push(indicator);                    // Column 7
+    formatted.push_str(&format!("{:<65}", code_part)); // Columns 8-72 (65 chars)
+    formatted.push_str(&card_seq_str);           // Columns 73-80
+    
+    // Ensure exactly 80 characters
+    format!("{:<80}", formatted.chars().take(80).collect::<String>())
+}
+
+/// The text a card reader sees: upper case, with characters the keypunch
+/// cannot punch left blank
+fn punched_text(text: &str, encoding_map: &HashMap<char, Vec<usize>>) -> String {
+    text.chars()
+        .map(|ch| {
+            let uppercase_ch = ch.to_uppercase().next().unwrap();
+            if encoding_map.contains_key(&uppercase_ch) { uppercase_ch } else { ' ' }
+        })
+        .collect()
+}
+
+/// One line of the deck, destined for one card
+enum DeckLine {
+    /// COBOL source, laid out into the fixed columns with its sequence number
+    Source { text: String, sequence: usize },
+    /// JCL or utility control statement, or a data card laid out by a
+    /// program card, punched verbatim from column 1
+    Control(String),
+    /// Card read from a card-image file, with its punches as found and the
+    /// characters they decode to
+    Punched { text: String, columns: Vec<Vec<usize>> },
+}
+
+/// Cards of one program's source within the deck (1-based, inclusive)
+struct ProgramRange {
+    name: String,
+    first_card: usize,
+    last_card: usize,
+}
+
+/// How a card fared when keyed a second time, as on an IBM 056 verifier
+enum Verification {
+    /// Both keyings agree; the card gets the OK notch in its right end
+    Matched,
+    /// Columns (1-based) where the keyings differ; each gets an error notch
+    /// in the top edge above it
+    Differed(Vec<usize>),
+    /// Never keyed a second time, so not verified; the card is not notched
+    Missing,
+}
+
+impl Verification {
+    /// The notches cut into a card, as triangles in template pixels
+    fn notches(&self, grid: &Calibration, image_width: f32) -> Vec<[(f32, f32); 3]> {
+        match self {
+            Verification::Matched => {
+                let (edge, middle) = (image_width, grid.punch_top(3) + grid.punch_height / 2.0);
+                vec![[
+                    (edge, middle - NOTCH_HALF_WIDTH_PX),
+                    (edge - NOTCH_DEPTH_PX, middle),
+                    (edge, middle + NOTCH_HALF_WIDTH_PX),
+                ]]
+            }
+            Verification::Differed(columns) => columns
+                .iter()
+                .map(|&col| {
+                    let middle = grid.punch_left(col - 1) + grid.punch_width / 2.0;
+                    [(middle - NOTCH_HALF_WIDTH_PX, 0.0), (middle, NOTCH_DEPTH_PX), (middle + NOTCH_HALF_WIDTH_PX, 0.0)]
+                })
+                .collect(),
+            Verification::Missing => Vec::new(),
+        }
+    }
+}
+
+/// Every card in the deck, in punching order
+struct Deck {
+    lines: Vec<DeckLine>,
+    programs: Vec<ProgramRange>,
+    /// Double-entry results by card index, for the cards that were verified
+    verification: HashMap<usize, Verification>,
+}
+
+impl DeckLine {
+    /// The 80 columns of the card as characters
+    fn card_text(&self) -> String {
+        match self {
+            DeckLine::Source { text, sequence } => format_cobol_line(text, *sequence),
+            DeckLine::Control(text) => format!("{:<80}", text.chars().take(COLUMNS).collect::<String>()),
+            DeckLine::Punched { text, .. } => text.clone(),
+        }
+    }
+}
+
+impl Deck {
+    /// An empty deck
+    fn new() -> Self {
+        Deck { lines: Vec::new(), programs: Vec::new(), verification: HashMap::new() }
+    }
+    
+    /// Card images as a card reader would read them back, one per card
+    fn card_images(&self, encoding_map: &HashMap<char, Vec<usize>>) -> Vec<String> {
+        self.lines.iter().map(|line| punched_text(&line.card_text(), encoding_map)).collect()
+    }
+    
+    /// The punches for every card
+    fn punch_cards(&self, encoding_map: &HashMap<char, Vec<usize>>) -> Vec<PunchCard> {
+        self.lines.iter().map(|line| PunchCard::from_deck_line(line, encoding_map)).collect()
+    }
+    
+    /// Whether any imported or binary card is of the given kind
+    fn has_punched(&self, kind: fn(&[Vec<usize>]) -> bool) -> bool {
+        self.lines
+            .iter()
+            .any(|line| matches!(line, DeckLine::Punched { columns, .. } if kind(columns)))
+    }
+    
+    /// Append cards read from a card-image file, decoding their punches
+    fn push_punched(&mut self, name: &str, cards: Vec<PunchCard>, decoding_map: &HashMap<Vec<usize>, char>) {
+        let first_card = self.lines.len() + 1;
+        self.lines.extend(cards.into_iter().map(|card| DeckLine::Punched {
+            text: card.decode(decoding_map),
+            columns: card.columns,
+        }));
+        self.programs.push(ProgramRange {
+            name: name.to_string(),
+            first_card,
+            last_card: self.lines.len(),
+        });
+    }
+    
+    /// Append data cards, already laid out into their columns
+    fn push_data(&mut self, name: &str, cards: Vec<String>) {
+        let first_card = self.lines.len() + 1;
+        self.lines.extend(cards.into_iter().map(DeckLine::Control));
+        self.programs.push(ProgramRange {
+            name: name.to_string(),
+            first_card,
+            last_card: self.lines.len(),
+        });
+    }
+    
+    /// Append a program's source cards, numbered from the start of that program
+    fn push_program(&mut self, name: &str, cobol_lines: &[String], increment: usize) {
+        let first_card = self.lines.len() + 1;
+        self.lines.extend(cobol_lines.iter().enumerate().map(|(idx, line)| DeckLine::Source {
+            text: line.clone(),
+            sequence: (idx + 1) * increment,
+        }));
+        self.programs.push(ProgramRange {
+            name: name.to_string(),
+            first_card,
+            last_card: self.lines.len(),
+        });
+    }
+}
+
 struct PunchCard {
     columns: Vec<Vec<usize>>,  // For each column, which rows to punch
 }
@@ -86,40 +355,7 @@ impl PunchCard {
     
     fn from_cobol_line(line: &str, sequence_num: usize, encoding_map: &HashMap<char, Vec<usize>>) -> Self {
         let mut card = PunchCard::new();
-        
-        // Format the line with proper COBOL columns:
-        // Columns 1-6: Sequence number (right-aligned, zero-padded)
-        // Column 7: Indicator area (preserved from input or space)
-        // Columns 8-72: COBOL code
-        // Columns 73-80: Sequence/identification (card sequence number)
-        
-        let sequence_str = format!("{:06}", sequence_num % 1000000);
-        let card_seq_str = format!("{:08}", sequence_num);
-        
-        // Check if line starts with spaces (typical COBOL indentation)
-        // If it starts with 7+ spaces, it's likely already formatted
-        let starts_with_spaces = line.starts_with("       "); // 7 spaces
-        
-        let (indicator, code_part) = if starts_with_spaces && line.len() > 7 {
-            // Line has leading spaces - treat as formatted
-            // Extract indicator from position 6 and code from position 7
-            let indicator = line.chars().nth(6).unwrap_or(' ');
-            let code = line[7..].trim_end();
-            (indicator, code.to_string())
-        } else {
-            // No leading spaces or too short - treat entire line as code
-            (' ', line.trim().to_string())
-        };
-        
-        // Build the full 80-column line
-        let mut formatted = String::with_capacity(80);
-        formatted.push_str(&sequence_str);           // Columns 1-6
-        formatted.push(indicator);                    // Column 7
-        formatted.push_str(&format!("{:<65}", code_part)); // Columns 8-72 (65 chars)
-        formatted.push_str(&card_seq_str);           // Columns 73-80
-        
-        // Ensure exactly 80 characters
-        let final_line = format!("{:<80}", formatted.chars().take(80).collect::<String>());
+        let final_line = format_cobol_line(line, sequence_num);
         
         // Encode each column
         for (col_idx, ch) in final_line.chars().enumerate() {
@@ -135,6 +371,38 @@ impl PunchCard {
         
         card
     }
+    
+    fn from_control_line(line: &str, encoding_map: &HashMap<char, Vec<usize>>) -> Self {
+        let mut card = PunchCard::new();
+        
+        // Control statements must start in column 1, so no sequence fields
+        for (col_idx, ch) in line.chars().take(COLUMNS).enumerate() {
+            let uppercase_ch = ch.to_uppercase().next().unwrap();
+            card.columns[col_idx] = encoding_map.get(&uppercase_ch).cloned().unwrap_or_default();
+        }
+        
+        card
+    }
+    
+    fn from_deck_line(line: &DeckLine, encoding_map: &HashMap<char, Vec<usize>>) -> Self {
+        match line {
+            DeckLine::Source { text, sequence } => PunchCard::from_cobol_line(text, *sequence, encoding_map),
+            DeckLine::Control(text) => PunchCard::from_control_line(text, encoding_map),
+            DeckLine::Punched { columns, .. } => PunchCard { columns: columns.clone() },
+        }
+    }
+    
+    /// Read the punches back as characters
+    fn decode(&self, decoding_map: &HashMap<Vec<usize>, char>) -> String {
+        self.columns
+            .iter()
+            .map(|punches| {
+                let mut rows = punches.clone();
+                rows.sort();
+                decoding_map.get(&rows).copied().unwrap_or(UNMAPPED_COLUMN)
+            })
+            .collect()
+    }
 }
 
 fn validate_and_format_cobol(lines: Vec<String>) -> Result<Vec<String>, String> {
@@ -182,60 +450,6 @@ fn validate_and_format_cobol(lines: Vec<String>) -> Result<Vec<String>, String>
     Ok(formatted_lines)
 }
 
-/// Generate JCL for compiling and running the COBOL program
-fn generate_jcl(program_name: &str, cobol_line_count: usize) -> Vec<String> {
-    let mut jcl = Vec::new();
-    
-    // Job card
-    jcl.push(format!("//{}    JOB (ACCT),'COBOL COMPILE',CLASS=A,MSGCLASS=A", 
-        program_name.to_uppercase()));
-    jcl.push("//             MSGLEVEL=(1,1),NOTIFY=&SYSUID".to_string());
-    
-    // Step 1: Compile the COBOL program
-    jcl.push("//*".to_string());
-    jcl.push("//COMPILE  EXEC PGM=IGYCRCTL,REGION=0M".to_string());
-    jcl.push("//STEPLIB  DD DSNAME=IGY.V6R3M0.SIGYCOMP,DISP=SHR".to_string());
-    jcl.push("//SYSPRINT DD SYSOUT=*".to_string());
-    jcl.push("//SYSLIN   DD DSNAME=&&LOADSET,DISP=(MOD,PASS),".to_string());
-    jcl.push("//            UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSUT1   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSUT2   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSUT3   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSUT4   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSUT5   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSUT6   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSUT7   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSIN    DD *".to_string());
-    
-    jcl.push(format!("//* {} COBOL SOURCE CARDS FOLLOW", cobol_line_count));
-    jcl.push("/*".to_string());
-    
-    // Step 2: Link-edit the compiled program
-    jcl.push("//*".to_string());
-    jcl.push("//LKED     EXEC PGM=IEWL,PARM='LIST,XREF,LET',".to_string());
-    jcl.push("//             REGION=1024K".to_string());
-    jcl.push("//SYSLIB   DD DSNAME=CEE.SCEELKED,DISP=SHR".to_string());
-    jcl.push("//SYSLIN   DD DSNAME=&&LOADSET,DISP=(OLD,DELETE)".to_string());
-    jcl.push("//SYSLMOD  DD DSNAME=&&GOSET(GO),DISP=(NEW,PASS),".to_string());
-    jcl.push("//            UNIT=SYSDA,SPACE=(CYL,(1,1,1))".to_string());
-    jcl.push("//SYSUT1   DD UNIT=SYSDA,SPACE=(CYL,(1,1))".to_string());
-    jcl.push("//SYSPRINT DD SYSOUT=*".to_string());
-    
-    // Step 3: Execute the program
-    jcl.push("//*".to_string());
-    jcl.push("//GO       EXEC PGM=*.LKED.SYSLMOD".to_string());
-    jcl.push("//STEPLIB  DD DSNAME=CEE.SCEERUN,DISP=SHR".to_string());
-    jcl.push("//SYSOUT   DD SYSOUT=*".to_string());
-    jcl.push("//SYSPRINT DD SYSOUT=*".to_string());
-    jcl.push("//SYSUDUMP DD SYSOUT=*".to_string());
-    jcl.push("//SYSIN    DD *".to_string());
-    jcl.push("//* INPUT DATA CARDS (IF ANY)".to_string());
-    jcl.push("/*".to_string());
-    jcl.push("//".to_string());
-    
-    jcl
-}
-
 /// Extract program name from COBOL source
 fn extract_program_name(cobol_lines: &[String]) -> String {
     for line in cobol_lines {
@@ -255,8 +469,55 @@ fn extract_program_name(cobol_lines: &[String]) -> String {
     "COBPROG".to_string() // Default name
 }
 
+/// Check the source for embedded EXEC SQL and EXEC CICS blocks, which need
+/// extra steps in the job before the compiler can see the program, and for
+/// static CALLs the link-edit step has to resolve
+fn describe_program(cobol_lines: &[String]) -> ProgramInfo {
+    let mut uses_sql = false;
+    let mut uses_cics = false;
+    let mut calls: Vec<String> = Vec::new();
+    let mut in_procedure_division = false;
+    
+    for line in cobol_lines {
+        // Skip comment lines
+        if matches!(line.chars().nth(6), Some('*') | Some('/')) {
+            continue;
+        }
+        
+        let upper = line.to_uppercase();
+        let words: Vec<&str> = upper.split_whitespace().collect();
+        for pair in words.windows(2) {
+            if pair[0] == "EXEC" || pair[0] == "EXECUTE" {
+                uses_sql |= pair[1] == "SQL";
+                uses_cics |= pair[1] == "CICS";
+            }
+            in_procedure_division |= pair[0] == "PROCEDURE" && pair[1].starts_with("DIVISION");
+            
+            // Only CALL 'literal' is static; CALL identifier is resolved at run time
+            if in_procedure_division && pair[0] == "CALL" {
+                let mut literal = pair[1].chars();
+                if let Some(quote @ ('\'' | '"')) = literal.next() {
+                    let name: String = literal.take_while(|&c| c != quote).collect();
+                    if !name.is_empty() && !calls.contains(&name) {
+                        calls.push(name);
+                    }
+                }
+            }
+        }
+    }
+    
+    ProgramInfo {
+        name: extract_program_name(cobol_lines),
+        card_count: cobol_lines.len(),
+        compile_options: Vec::new(),
+        uses_sql,
+        uses_cics,
+        calls,
+    }
+}
+
 /// Generate a text representation like a coding sheet
-fn generate_coding_sheet(cobol_lines: &[String]) -> String {
+fn generate_coding_sheet(deck: &Deck) -> String {
     let mut output = String::new();
     
     // Header
@@ -267,8 +528,68 @@ fn generate_coding_sheet(cobol_lines: &[String]) -> String {
     output.push_str("1-6   78       16      24      32      40      48      56      64       73-80   \n");
     output.push_str("--------------------------------------------------------------------------------\n");
     
-    for (idx, line) in cobol_lines.iter().enumerate() {
-        let sequence_num = idx + 1;
+    // With several programs in the deck, mark where each one starts
+    let show_programs = deck.programs.len() > 1;
+    
+    for (idx, deck_line) in deck.lines.iter().enumerate() {
+        if show_programs {
+            for program in deck.programs.iter().filter(|p| p.first_card == idx + 1 && p.last_card >= p.first_card) {
+                let title = format!("-- PROGRAM {} (CARDS {}-{}) ", program.name, program.first_card, program.last_card);
+                output.push_str(&format!("{:-<80}\n", title));
+            }
+        }
+        
+        let (line, sequence_num) = match deck_line {
+            DeckLine::Source { text, sequence } => (text, *sequence),
+            DeckLine::Control(text) => {
+                // Control statements are punched as-is
+                output.push_str(&format!("{}\n", text));
+                continue;
+            }
+            DeckLine::Punched { columns, .. } if binary_deck::is_binary_card(columns) => {
+                // Column-binary cards are shown as one octal word per column
+                output.push_str(&format!("COLUMN BINARY CARD {}\n", idx + 1));
+                for words in columns.chunks(16) {
+                    let octal: Vec<String> = words
+                        .iter()
+                        .map(|punches| format!("{:04o}", card_image::column_bits(punches)))
+                        .collect();
+                    output.push_str(&format!("{}\n", octal.join(" ")));
+                }
+                continue;
+            }
+            DeckLine::Punched { columns, .. } if object_deck::is_object_card(columns) => {
+                // Object deck cards are shown as EBCDIC with each column's hex
+                // beneath, zone digit over numeric digit
+                let bytes: Vec<Option<u8>> = columns
+                    .iter()
+                    .map(|punches| ebcdic::from_card_code(card_image::column_bits(punches)))
+                    .collect();
+                let shown: String = bytes
+                    .iter()
+                    .map(|byte| match byte.and_then(ebcdic::to_char) {
+                        Some(ch) if ch.is_ascii_graphic() || ch == ' ' => ch,
+                        _ => '.',
+                    })
+                    .collect();
+                let nibble = |byte: &Option<u8>, shift: u8| byte.map_or('?', |b| char::from_digit((b >> shift & 0x0F) as u32, 16).unwrap().to_ascii_uppercase());
+                output.push_str(&format!("{}\n", shown.trim_end()));
+                output.push_str(&format!("{}\n", bytes.iter().map(|b| nibble(b, 4)).collect::<String>()));
+                output.push_str(&format!("{}\n", bytes.iter().map(|b| nibble(b, 0)).collect::<String>()));
+                continue;
+            }
+            DeckLine::Punched { text, columns } => {
+                // Imported cards are shown as read, with the punches of any
+                // column that is no character spelled out beneath
+                output.push_str(&format!("{}\n", text.trim_end()));
+                for (col_idx, (ch, punches)) in text.chars().zip(columns).enumerate() {
+                    if ch == UNMAPPED_COLUMN {
+                        output.push_str(&format!("      column {:>2} punched {}\n", col_idx + 1, punch_pattern(punches)));
+                    }
+                }
+                continue;
+            }
+        };
         
         // Use the same logic as PunchCard::from_cobol_line
         let starts_with_spaces = line.starts_with("       "); // 7 spaces
@@ -294,76 +615,442 @@ fn generate_coding_sheet(cobol_lines: &[String]) -> String {
     }
     
     output.push_str("================================================================================\n");
-    output.push_str(&format!("Total Cards: {}\n", cobol_lines.len()));
+    output.push_str(&format!("Total Cards: {}\n", deck.lines.len()));
+    if show_programs {
+        for program in &deck.programs {
+            output.push_str(&format!("  {:<30} cards {:>5} - {:>5} ({} cards)\n",
+                program.name,
+                program.first_card,
+                program.last_card,
+                program.last_card + 1 - program.first_card
+            ));
+        }
+    }
     output.push_str("================================================================================\n");
     
     output
 }
 
-fn generate_punch_card_pdf(
-    cobol_lines: Vec<String>,
-    template_path: &str,
-    output_path: &str,
-    coding_sheet_path: &str,
-    include_jcl: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    
-    let encoding_map = get_hollerith_encoding();
+/// Read a coding sheet back into the punches of every card it lists
+fn read_coding_sheet(text: &str, encoding_map: &HashMap<char, Vec<usize>>) -> Result<Vec<PunchCard>, String> {
+    let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+    if lines.len() < 6 || !lines[1].iter().collect::<String>().contains("CODING SHEET") {
+        return Err("not a coding sheet (no CODING SHEET header)".to_string());
+    }
     
-    // Extract program name and generate JCL if requested
-    let mut all_lines = Vec::new();
+    let is_rule = |line: &[char]| line.len() == COLUMNS && line.iter().all(|&c| c == '=');
+    let is_hex_row = |line: Option<&Vec<char>>| {
+        line.is_some_and(|l| l.len() == COLUMNS && l.iter().all(|c| c.is_ascii_hexdigit() || *c == '?'))
+    };
+    // SEQ  I  CODE(65)  CARD: six digits, the indicator, the code, eight digits
+    let source_parts = |line: &[char]| -> Option<String> {
+        let len = line.len();
+        let digits = |range: std::ops::Range<usize>| line[range].iter().all(char::is_ascii_digit);
+        if len < 86 || !digits(0..6) || !digits(len - 8..len) {
+            return None;
+        }
+        let gaps = [6, 7, 9, 10, len - 10, len - 9];
+        if gaps.iter().any(|&idx| line[idx] != ' ') {
+            return None;
+        }
+        let code: String = line[11..len - 10].iter().collect();
+        let card: String = format!(
+            "{}{}{:<65}{}",
+            line[..6].iter().collect::<String>(),
+            line[8],
+            code.trim_end(),
+            line[len - 8..].iter().collect::<String>()
+        );
+        Some(card.chars().take(COLUMNS).collect())
+    };
     
-    if include_jcl {
-        let program_name = extract_program_name(&cobol_lines);
-        println!("Program name detected: {}", program_name);
+    let mut cards: Vec<PunchCard> = Vec::new();
+    let mut idx = 6;
+    while idx < lines.len() && !is_rule(&lines[idx]) {
+        let line = &lines[idx];
+        let text: String = line.iter().collect();
+        idx += 1;
         
-        let jcl_lines = generate_jcl(&program_name, cobol_lines.len());
+        if text.starts_with("-- PROGRAM ") {
+            continue;
+        }
+        
+        if let Some(detail) = text.strip_prefix("      column ") {
+            // Punches of an imported column that is no character
+            let (column, pattern) = detail
+                .split_once(" punched ")
+                .and_then(|(col, pat)| Some((col.trim().parse::<usize>().ok()?, parse_punch_pattern(pat.trim())?)))
+                .ok_or_else(|| format!("line {}: unreadable column punches: {}", idx, text.trim()))?;
+            let card = cards
+                .last_mut()
+                .filter(|_| (1..=COLUMNS).contains(&column))
+                .ok_or_else(|| format!("line {}: column punches without a card", idx))?;
+            card.columns[column - 1] = pattern;
+            continue;
+        }
         
-        // Add JCL header cards
-        for line in &jcl_lines {
-            // Stop before the marker for COBOL source
-            if line.contains("COBOL SOURCE CARDS FOLLOW") {
-                break;
+        if text.starts_with("COLUMN BINARY CARD ") {
+            // One octal word per column, 16 to a line
+            let words: String = lines
+                .get(idx..idx + COLUMNS / 16)
+                .ok_or_else(|| format!("line {}: binary card is cut short", idx))?
+                .iter()
+                .map(|row| row.iter().collect::<String>() + " ")
+                .collect();
+            let mut card = PunchCard::new();
+            for (col_idx, word) in words.split_whitespace().take(COLUMNS).enumerate() {
+                let bits = u16::from_str_radix(word, 8).map_err(|_| format!("line {}: bad octal word {}", idx, word))?;
+                card.columns[col_idx] = card_image::column_punches(bits);
             }
-            all_lines.push(line.clone());
+            cards.push(card);
+            idx += COLUMNS / 16;
+            continue;
         }
         
-        // Add COBOL source cards
-        all_lines.extend(cobol_lines.clone());
+        if is_hex_row(lines.get(idx)) && is_hex_row(lines.get(idx + 1)) {
+            // Object card: zone digits over numeric digits beneath the text
+            let mut card = PunchCard::new();
+            for (col_idx, (zone, digit)) in lines[idx].iter().zip(&lines[idx + 1]).enumerate() {
+                let byte = zone
+                    .to_digit(16)
+                    .zip(digit.to_digit(16))
+                    .map(|(hi, lo)| (hi << 4 | lo) as u8)
+                    .ok_or_else(|| format!("line {}: column {} is no EBCDIC byte", idx, col_idx + 1))?;
+                card.columns[col_idx] = card_image::column_punches(ebcdic::card_code(byte));
+            }
+            cards.push(card);
+            idx += 2;
+            continue;
+        }
         
-        // Add remaining JCL cards (after the COBOL source marker)
-        let mut after_marker = false;
-        for line in &jcl_lines {
-            if after_marker {
-                all_lines.push(line.clone());
+        // Source cards are spread over the sheet's columns; control
+        // statements and imported cards are shown as punched
+        let card_text = source_parts(line).unwrap_or(text);
+        cards.push(PunchCard::from_control_line(&card_text, encoding_map));
+    }
+    
+    Ok(cards)
+}
+
+/// Lay out the deck: each program's COBOL source cards, wrapped in a job if requested
+fn assemble_deck(
+    sources: &[Vec<String>],
+    jcl_kind: Option<JclKind>,
+    profile: &JclProfile,
+    layout: JobLayout,
+) -> Result<Deck, String> {
+    let increment = jcl_kind.map_or(1, JclKind::sequence_increment);
+    let mut programs: Vec<ProgramInfo> = sources.iter().map(|lines| describe_program(lines)).collect();
+    let mut deck = Deck::new();
+    
+    let Some(kind) = jcl_kind else {
+        for (program, lines) in programs.iter().zip(sources) {
+            deck.push_program(&program.name, lines, increment);
+        }
+        println!("Total cards (COBOL only): {}", deck.lines.len());
+        return Ok(deck);
+    };
+    
+    let mut sources = sources.to_vec();
+    for (program, lines) in programs.iter_mut().zip(sources.iter_mut()) {
+        println!("Program name detected: {}", program.name);
+        if kind != JclKind::Compile {
+            continue;
+        }
+        if profile.dialect == Dialect::Zos && program.uses_sql {
+            println!("EXEC SQL detected: adding DB2 {}", if profile.sql_coprocessor { "coprocessor" } else { "precompile step" });
+        }
+        if profile.dialect == Dialect::Zos && program.uses_cics {
+            println!("EXEC CICS detected: adding CICS translate step");
+        }
+        
+        // CBL/PROCESS options in the source go into the compile step's PARM=
+        let resolved = compiler_options::resolve(&profile.compile_options, lines);
+        for warning in &resolved.warnings {
+            eprintln!("Warning: {}: {}", program.name, warning);
+        }
+        let mut parm = resolved.parm;
+        
+        // The MVS 3.8j compiler only knows COBOL-68 and its own option names
+        if profile.dialect == Dialect::Mvs38j {
+            for warning in cobol68::check(lines) {
+                eprintln!("Warning: {}: {}", program.name, warning);
+            }
+            let card_count = lines.len();
+            lines.retain(|line| !cobol68::is_directive(line));
+            if lines.len() < card_count {
+                eprintln!(
+                    "Warning: {}: {} CBL/PROCESS or >> directive cards left out of the deck, as IKFCBL00 rejects them",
+                    program.name,
+                    card_count - lines.len()
+                );
+                program.card_count = lines.len();
             }
-            if line.contains("COBOL SOURCE CARDS FOLLOW") {
-                after_marker = true;
+            let (translated, dropped) = compiler_options::to_ans_cobol(&parm);
+            if !dropped.is_empty() {
+                eprintln!("Warning: {}: options left out for MVS 3.8j: {}", program.name, dropped.join(","));
             }
+            parm = translated;
         }
         
-        println!("Total cards (with JCL): {}", all_lines.len());
+        if parm.join(",").len() > jcl::MAX_PARM_LENGTH {
+            eprintln!("Warning: {}: compiler options exceed the {}-character PARM= limit", program.name, jcl::MAX_PARM_LENGTH);
+        }
+        program.compile_options = parm;
+    }
+    
+    // Flag calls the link-edit step cannot resolve before any card is produced
+    if kind == JclKind::Compile {
+        for problem in jcl::check_calls(&programs, profile, layout)? {
+            eprintln!("Warning: {}", problem);
+        }
+    }
+    
+    // Each source marker in the JCL is replaced by the next program's cards
+    let mut next_program = programs.iter().zip(&sources);
+    for line in jcl::generate(kind, &programs, profile, layout) {
+        if line.contains(jcl::SOURCE_MARKER) {
+            if let Some((program, lines)) = next_program.next() {
+                deck.push_program(&program.name, lines, increment);
+            }
+        } else {
+            deck.lines.push(DeckLine::Control(line));
+        }
+    }
+    
+    println!("Total cards (with JCL): {}", deck.lines.len());
+    Ok(deck)
+}
+
+/// Compare each program's source or data cards against a second keying of
+/// the same input by another operator, column by column, and record the
+/// outcome for every card
+fn verify_double_entry(
+    deck: &mut Deck,
+    second_keying: &[Vec<String>],
+    encoding_map: &HashMap<char, Vec<usize>>,
+) {
+    let mut differing = 0;
+    
+    for (program_idx, second_lines) in second_keying.iter().enumerate() {
+        let Some(range) = deck.programs.get(program_idx) else { break };
+        let (name, first_card, last_card) = (range.name.clone(), range.first_card, range.last_card);
+        let card_count = last_card + 1 - first_card;
+        if second_lines.len() != card_count {
+            eprintln!("Warning: {}: first keying has {} cards, second keying {}", name, card_count, second_lines.len());
+        }
+        
+        for (offset, card_idx) in (first_card - 1..last_card).enumerate() {
+            // The second keying goes into the same columns as the first
+            let second_text = match &deck.lines[card_idx] {
+                &DeckLine::Source { sequence, .. } => second_lines.get(offset).map(|line| format_cobol_line(line, sequence)),
+                DeckLine::Control(_) => second_lines.get(offset).map(|line| format!("{:<80}", line)),
+                DeckLine::Punched { .. } => continue,
+            };
+            let Some(second_text) = second_text else {
+                eprintln!("Card {} ({} line {}): not keyed a second time", card_idx + 1, name, offset + 1);
+                deck.verification.insert(card_idx, Verification::Missing);
+                continue;
+            };
+            let first_text = deck.lines[card_idx].card_text();
+            let first = PunchCard::from_control_line(&first_text, encoding_map);
+            let second = PunchCard::from_control_line(&second_text, encoding_map);
+            
+            let columns: Vec<usize> = first
+                .columns
+                .iter()
+                .zip(&second.columns)
+                .enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(|(col_idx, _)| col_idx + 1)
+                .collect();
+            if columns.is_empty() {
+                deck.verification.insert(card_idx, Verification::Matched);
+                continue;
+            }
+            
+            let keyed = |text: &str, col: usize| text.chars().nth(col - 1).unwrap_or(' ');
+            let shown: Vec<String> = columns
+                .iter()
+                .map(|&col| format!("{} '{}'/'{}'", col, keyed(&first_text, col), keyed(&second_text, col)))
+                .collect();
+            eprintln!("Card {} ({} line {}): keyings differ in column {}", card_idx + 1, name, offset + 1, shown.join(", "));
+            differing += 1;
+            deck.verification.insert(card_idx, Verification::Differed(columns));
+        }
+    }
+    
+    let missing = deck.verification.values().filter(|verification| matches!(verification, Verification::Missing)).count();
+    if missing > 0 {
+        eprintln!("Warning: {} cards were not keyed a second time; they are left unverified and unnotched", missing);
+    }
+    let verified = deck.verification.len() - missing;
+    if differing > 0 {
+        eprintln!("Warning: {} of {} verified cards differ between keyings; they carry error notches", differing, verified);
     } else {
-        all_lines = cobol_lines.clone();
-        println!("Total cards (COBOL only): {}", all_lines.len());
+        println!("✓ All {} verified cards match", verified);
+    }
+}
+
+/// Read card-image files into a deck, one program range per file
+fn import_deck(paths: &[String], format: Option<CardImageFormat>) -> Result<Deck, Box<dyn std::error::Error>> {
+    let encoding_map = get_hollerith_encoding();
+    let decoding_map = get_hollerith_decoding();
+    let mut deck = Deck::new();
+    
+    for path in paths {
+        let format = format.unwrap_or_else(|| CardImageFormat::from_path(path));
+        println!("Reading card images: {} ({:?})", path, format);
+        let cards = card_image::read_deck(&fs::read(path)?, format, &encoding_map)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map_or_else(|| path.clone(), |file| file.to_string_lossy().to_uppercase());
+        let first_card = deck.lines.len();
+        deck.push_punched(&name, cards, &decoding_map);
+        
+        let unmapped = deck.lines[first_card..]
+            .iter()
+            .filter(|line| match line {
+                DeckLine::Punched { columns, .. }
+                    if binary_deck::is_binary_card(columns) || object_deck::is_object_card(columns) => false,
+                line => line.card_text().contains(UNMAPPED_COLUMN),
+            })
+            .count();
+        if unmapped > 0 {
+            eprintln!("Warning: {}: {} cards have columns that are no known character; their punches are listed on the coding sheet", path, unmapped);
+        }
+    }
+    
+    println!("Total cards (imported): {}", deck.lines.len());
+    Ok(deck)
+}
+
+/// Serialize drawing operations into a content stream
+fn encode_content(operations: Vec<(String, Vec<lopdf::Object>)>) -> Vec<u8> {
+    use lopdf::Object;
+    
+    let mut content_data = Vec::new();
+    for (operator, operands) in operations {
+        for operand in operands {
+            // Manually serialize Object to bytes
+            match operand {
+                Object::Integer(i) => content_data.extend_from_slice(i.to_string().as_bytes()),
+                Object::Real(f) => content_data.extend_from_slice(f.to_string().as_bytes()),
+                Object::Name(ref n) => {
+                    content_data.push(b'/');
+                    content_data.extend_from_slice(n);
+                },
+                Object::String(ref s, _) => {
+                    content_data.push(b'(');
+                    content_data.extend_from_slice(s);
+                    content_data.push(b')');
+                },
+                Object::Reference(r) => {
+                    content_data.extend_from_slice(r.0.to_string().as_bytes());
+                    content_data.push(b' ');
+                    content_data.extend_from_slice(r.1.to_string().as_bytes());
+                    content_data.push(b' ');
+                    content_data.push(b'R');
+                },
+                _ => {},
+            }
+            content_data.push(b' ');
+        }
+        content_data.extend_from_slice(operator.as_bytes());
+        content_data.push(b'\n');
+    }
+    content_data
+}
+
+/// A page's or form's font resources: the base-14 faces need no embedding
+fn font_resources(fonts: &[(&str, &str)]) -> lopdf::Object {
+    use lopdf::{Dictionary, Object};
+    
+    let mut dict = Dictionary::new();
+    for &(name, base_font) in fonts {
+        let mut font = Dictionary::new();
+        font.set("Type", Object::Name(b"Font".to_vec()));
+        font.set("Subtype", Object::Name(b"Type1".to_vec()));
+        font.set("BaseFont", Object::Name(base_font.as_bytes().to_vec()));
+        font.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+        dict.set(name, Object::Dictionary(font));
+    }
+    Object::Dictionary(dict)
+}
+
+/// What is drawn on the cards besides the template and the punches
+#[derive(Default)]
+struct PdfOptions {
+    /// Print each column's character along the top edge, as the 029 does
+    interpret: bool,
+    /// Lines of IBM 557 interpreter printing across the card
+    interpreter_lines: Vec<InterpreterLine>,
+    /// Draw the card itself instead of using the template image
+    vector_card: bool,
+    /// Columns to rule a line after, on a vector-drawn card
+    rulings: Vec<usize>,
+    /// Corrections for the printer the PDF is for
+    printer: PrinterProfile,
+    /// Page size and how the cards are set on it
+    layout: Layout,
+    /// Draw crop marks around each card for cutting
+    crop_marks: bool,
+    /// Draw the punches as `CutContour` lines for a cutting table instead
+    /// of black, and the card's outline too if asked
+    cut_contour: bool,
+    cut_contour_outline: bool,
+    /// Radius of the cut card's corners, for its outline
+    corner_radius_mm: f32,
+}
+
+/// What the cards are drawn on: the template image, or none for a card drawn
+/// as vectors, with its size in pixels and its punch grid
+struct CardTemplate {
+    image: Option<image::RgbImage>,
+    size: (u32, u32),
+    grid: Calibration,
+}
+
+/// Load the template, or the drawn card's size and grid without one
+fn load_template(template_path: &str, vector_card: bool) -> Result<CardTemplate, Box<dyn std::error::Error>> {
+    let img_rgb = if vector_card { None } else { Some(image::open(template_path)?.to_rgb8()) };
+    let (img_width, img_height) = match &img_rgb {
+        Some(img_rgb) => img_rgb.dimensions(),
+        None => (vector_card::WIDTH_PX as u32, vector_card::HEIGHT_PX as u32),
+    };
+    let grid = if vector_card { Calibration::BUNDLED } else { calibration::load(template_path)? };
+    if !grid.fits(img_width, img_height) {
+        return Err(format!("The punch grid does not fit on template {}; run calibrate on it", template_path).into());
     }
+    Ok(CardTemplate { image: img_rgb, size: (img_width, img_height), grid })
+}
+
+fn generate_punch_card_pdf(
+    deck: &Deck,
+    template_path: &str,
+    output_path: &str,
+    coding_sheet_path: &str,
+    options: &PdfOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    
+    let encoding_map = get_hollerith_encoding();
     
     // Generate coding sheet text file
-    let coding_sheet_text = generate_coding_sheet(&all_lines);
+    let coding_sheet_text = generate_coding_sheet(deck);
     fs::write(coding_sheet_path, coding_sheet_text)?;
     println!("✓ Coding sheet generated: {}", coding_sheet_path);
     
-    // Load template image
-    let img = image::open(template_path)?;
-    let img_rgb = img.to_rgb8();
-    let (img_width, img_height) = img_rgb.dimensions();
+    // Load template image, unless the card is to be drawn as vectors
+    let vector_card = options.vector_card || !std::path::Path::new(template_path).exists();
+    if vector_card && !options.vector_card {
+        eprintln!("Warning: template {} not found; drawing the card without it", template_path);
+    }
+    let CardTemplate { image: img_rgb, size: (img_width, img_height), grid } = load_template(template_path, vector_card)?;
     
     // Convert cards with sequence numbers
-    let cards: Vec<PunchCard> = all_lines
-        .iter()
-        .enumerate()
-        .map(|(idx, line)| PunchCard::from_cobol_line(line, idx + 1, &encoding_map))
-        .collect();
+    let cards = deck.punch_cards(&encoding_map);
     
     // Use lopdf for manual PDF construction
     use lopdf::{Document, Object, Stream, Dictionary};
@@ -371,34 +1058,80 @@ fn generate_punch_card_pdf(
     let mut doc = Document::with_version("1.5");
     
     // Calculate dimensions in points
-    let page_width = A4_WIDTH_MM * PT_PER_MM;
-    let page_height = A4_HEIGHT_MM * PT_PER_MM;
-    let card_width_pt = CARD_WIDTH_MM * PT_PER_MM;
-    let card_height_pt = CARD_HEIGHT_MM * PT_PER_MM;
-    let margin_left = ((A4_WIDTH_MM - CARD_WIDTH_MM) / 2.0) * PT_PER_MM;
-    let spacing = ((A4_HEIGHT_MM - (CARD_HEIGHT_MM * CARDS_PER_PAGE as f32)) / (CARDS_PER_PAGE as f32 + 1.0)) * PT_PER_MM;
+    let layout = &options.layout;
+    let (page_width, page_height) = (layout.page_width, layout.page_height);
+    let (card_width_pt, card_height_pt) = (layout.card_width, layout.card_height);
+    
+    // Bleed is printed in the colour of the card stock: on a template, the
+    // median of its pixels, as most of a card is bare stock
+    let bleed_colour = match &img_rgb {
+        _ if layout.bleed <= 0.0 => None,
+        Some(img_rgb) => Some(std::array::from_fn::<f32, 3, _>(|channel| {
+            let mut values: Vec<u8> = img_rgb.pixels().map(|pixel| pixel.0[channel]).collect();
+            values.sort_unstable();
+            values[values.len() / 2] as f32 / 255.0
+        })),
+        None => Some(vector_card::CARD_COLOUR),
+    };
     
     // Add template image as XObject
-    let image_data = img_rgb.as_raw().clone();
-    let mut image_dict = Dictionary::new();
-    image_dict.set("Type", Object::Name(b"XObject".to_vec()));
-    image_dict.set("Subtype", Object::Name(b"Image".to_vec()));
-    image_dict.set("Width", Object::Integer(img_width as i64));
-    image_dict.set("Height", Object::Integer(img_height as i64));
-    image_dict.set("ColorSpace", Object::Name(b"DeviceRGB".to_vec()));
-    image_dict.set("BitsPerComponent", Object::Integer(8));
-    
-    let image_stream = Stream::new(image_dict, image_data);
+    let image_stream = match img_rgb {
+        Some(img_rgb) => {
+            let image_data = img_rgb.into_raw();
+            let mut image_dict = Dictionary::new();
+            image_dict.set("Type", Object::Name(b"XObject".to_vec()));
+            image_dict.set("Subtype", Object::Name(b"Image".to_vec()));
+            image_dict.set("Width", Object::Integer(img_width as i64));
+            image_dict.set("Height", Object::Integer(img_height as i64));
+            image_dict.set("ColorSpace", Object::Name(b"DeviceRGB".to_vec()));
+            image_dict.set("BitsPerComponent", Object::Integer(8));
+            Stream::new(image_dict, image_data)
+        }
+        None => {
+            // A form drawn in template pixels, scaled to the unit square an
+            // image fills so both are placed alike
+            let mut form_resources = Dictionary::new();
+            form_resources.set("Font", font_resources(&[(vector_card::FONT, "Helvetica")]));
+            
+            let (width, height) = (vector_card::WIDTH_PX, vector_card::HEIGHT_PX);
+            let mut form_dict = Dictionary::new();
+            form_dict.set("Type", Object::Name(b"XObject".to_vec()));
+            form_dict.set("Subtype", Object::Name(b"Form".to_vec()));
+            form_dict.set("BBox", vec![0.into(), 0.into(), width.into(), height.into()]);
+            form_dict.set("Matrix", vec![(1.0 / width).into(), 0.into(), 0.into(), (1.0 / height).into(), 0.into(), 0.into()]);
+            form_dict.set("Resources", Object::Dictionary(form_resources));
+            Stream::new(form_dict, encode_content(vector_card::card_operations(&options.rulings)))
+        }
+    };
     let image_id = doc.add_object(image_stream);
     
     // Process cards in pages
-    for page_cards in cards.chunks(CARDS_PER_PAGE) {
-        let mut operations = Vec::new();
+    for page_cards in layout.impose(cards.len()) {
+        // The printer's correction applies to everything on the page
+        let mut operations: Vec<(String, Vec<Object>)> = options.printer.page_transform(page_width, page_height).into_iter().collect();
         
         // Draw each card on this page
-        for (card_position, card) in page_cards.iter().enumerate() {
-            // Calculate Y position (from bottom in PDF coordinates)
-            let y_pos = page_height - spacing - ((card_position as f32 + 1.0) * card_height_pt) - (card_position as f32 * spacing);
+        for (card_position, &card_idx) in page_cards.iter().enumerate() {
+            let card = &cards[card_idx];
+            
+            // Each card is drawn upright from the origin and moved into its
+            // place, turned if the layout turns it
+            operations.push(("q".to_string(), vec![]));
+            operations.push(("cm".to_string(), layout.card_transform(card_position).iter().map(|&n| n.into()).collect()));
+            
+            // Bleed: the card's colour carried past its edges, traced as a
+            // path so it is not taken for a punch
+            if let Some(bleed_colour) = bleed_colour {
+                let (left, bottom) = (-layout.bleed, -layout.bleed);
+                let (right, top) = (card_width_pt + layout.bleed, card_height_pt + layout.bleed);
+                operations.push(("rg".to_string(), bleed_colour.iter().map(|&n| n.into()).collect()));
+                operations.push(("m".to_string(), vec![left.into(), bottom.into()]));
+                for (x, y) in [(right, bottom), (right, top), (left, top)] {
+                    operations.push(("l".to_string(), vec![x.into(), y.into()]));
+                }
+                operations.push(("h".to_string(), vec![]));
+                operations.push(("f".to_string(), vec![]));
+            }
             
             // Draw template image
             operations.push(("q".to_string(), vec![])); // Save graphics state
@@ -409,8 +1142,8 @@ fn generate_punch_card_pdf(
                     0.0.into(),
                     0.0.into(),
                     card_height_pt.into(),
-                    margin_left.into(),
-                    y_pos.into(),
+                    0.0.into(),
+                    0.0.into(),
                 ],
             )); // Transform matrix
             operations.push(("Do".to_string(), vec![Object::Name(format!("Im{}", image_id.0).into_bytes())]));
@@ -421,76 +1154,93 @@ fn generate_punch_card_pdf(
             let scale_x = card_width_pt / img_width as f32;
             let scale_y = card_height_pt / img_height as f32;
             
-            let punch_width_pt = PUNCH_WIDTH_PX * scale_x;
-            let punch_height_pt = PUNCH_HEIGHT_PX * scale_y;
-            
-            // Set black fill color
-            operations.push(("rg".to_string(), vec![0.0.into(), 0.0.into(), 0.0.into()]));
+            // Set black fill color, or for a cutting table the thinnest
+            // line in its spot colour
+            let paint = if options.cut_contour {
+                operations.push(("CS".to_string(), vec![Object::Name(cutter::CUT_CONTOUR.as_bytes().to_vec())]));
+                operations.push(("SCN".to_string(), vec![1.0.into()]));
+                operations.push(("w".to_string(), vec![0.0.into()]));
+                "S"
+            } else {
+                operations.push(("rg".to_string(), vec![0.0.into(), 0.0.into(), 0.0.into()]));
+                "f"
+            };
             
-            // Draw punches as black rectangles using template coordinates
+            // Draw punches as rectangles, placed as the cutter places them
             for (col_idx, punches) in card.columns.iter().enumerate() {
                 for &row_idx in punches {
-                    // Calculate position based on template pixel coordinates
-                    let punch_x_px = FIRST_PUNCH_X + (col_idx as f32 * COLUMN_SPACING);
-                    let punch_y_px = FIRST_PUNCH_Y + (row_idx as f32 * ROW_SPACING);
-                    
-                    // Convert template image coordinates to PDF coordinates
-                    // X: straightforward - template X position scaled and offset by card position
-                    let x = margin_left + (punch_x_px * scale_x);
+                    let (x, y, width, height) = cutter::punch_rect(&grid, (img_width as f32, img_height as f32), col_idx, row_idx);
                     
-                    // Y: The template image is drawn with its bottom-left at y_pos
-                    // In the image, Y increases downward from top
-                    // In PDF, Y increases upward from bottom
-                    // So: PDF_Y = y_pos + (img_height - template_Y - punch_height) * scale
-                    let punch_y = y_pos + ((img_height as f32 - punch_y_px) * scale_y) - punch_height_pt;
-                    
-                    // Rectangle: x y width height re
-                    operations.push((
-                        "re".to_string(),
-                        vec![
-                            x.into(),
-                            punch_y.into(),
-                            punch_width_pt.into(),
-                            punch_height_pt.into(),
-                        ],
-                    ));
-                    operations.push(("f".to_string(), vec![])); // Fill
+                    // Rectangle: x y width height re, in points
+                    let rect = [x, y, width, height].map(|mm| (mm * PT_PER_MM).into());
+                    operations.push(("re".to_string(), rect.to_vec()));
+                    operations.push((paint.to_string(), vec![]));
                 }
             }
+            
+            if options.interpret || !options.interpreter_lines.is_empty() {
+                let text = deck.lines[card_idx].card_text();
+                let place = |px: f32, py: f32| (px * scale_x, (img_height as f32 - py) * scale_y);
+                operations.extend(interpret::card_operations(
+                    &text,
+                    options.interpret,
+                    &options.interpreter_lines,
+                    &grid,
+                    &encoding_map,
+                    place,
+                    scale_x,
+                ));
+            }
+            
+            // Verifier notches are cut out of the card edge, so they are
+            // drawn in the paper's white: the OK notch in the right end, an
+            // error notch in the top edge above each column in error
+            let notches = deck
+                .verification
+                .get(&card_idx)
+                .map_or_else(Vec::new, |verification| verification.notches(&grid, img_width as f32));
+            if !notches.is_empty() {
+                operations.push(("rg".to_string(), vec![1.0.into(), 1.0.into(), 1.0.into()]));
+            }
+            for notch in notches {
+                for (point_idx, (px, py)) in notch.into_iter().enumerate() {
+                    let operator = if point_idx == 0 { "m" } else { "l" };
+                    let x = px * scale_x;
+                    let y = (img_height as f32 - py) * scale_y;
+                    operations.push((operator.to_string(), vec![x.into(), y.into()]));
+                }
+                operations.push(("h".to_string(), vec![]));
+                operations.push(("f".to_string(), vec![]));
+            }
+            
+            // The card's outline for the cutting table, traced as a path
+            // like the bleed
+            if options.cut_contour_outline {
+                operations.push(("CS".to_string(), vec![Object::Name(cutter::CUT_CONTOUR.as_bytes().to_vec())]));
+                operations.push(("SCN".to_string(), vec![1.0.into()]));
+                operations.push(("w".to_string(), vec![0.0.into()]));
+                for (point_idx, (x, y)) in cutter::card_outline(options.corner_radius_mm, 0.0)?.into_iter().enumerate() {
+                    let operator = if point_idx == 0 { "m" } else { "l" };
+                    operations.push((operator.to_string(), vec![(x * PT_PER_MM).into(), (y * PT_PER_MM).into()]));
+                }
+                operations.push(("h".to_string(), vec![]));
+                operations.push(("S".to_string(), vec![]));
+            }
+            operations.push(("Q".to_string(), vec![]));
         }
         
-        // Encode operations into content stream
-        let mut content_data = Vec::new();
-        for (operator, operands) in operations {
-            for operand in operands {
-                // Manually serialize Object to bytes
-                match operand {
-                    Object::Integer(i) => content_data.extend_from_slice(i.to_string().as_bytes()),
-                    Object::Real(f) => content_data.extend_from_slice(f.to_string().as_bytes()),
-                    Object::Name(ref n) => {
-                        content_data.push(b'/');
-                        content_data.extend_from_slice(n);
-                    },
-                    Object::String(ref s, _) => {
-                        content_data.push(b'(');
-                        content_data.extend_from_slice(s);
-                        content_data.push(b')');
-                    },
-                    Object::Reference(r) => {
-                        content_data.extend_from_slice(r.0.to_string().as_bytes());
-                        content_data.push(b' ');
-                        content_data.extend_from_slice(r.1.to_string().as_bytes());
-                        content_data.push(b' ');
-                        content_data.push(b'R');
-                    },
-                    _ => {},
-                }
-                content_data.push(b' ');
+        if options.crop_marks {
+            operations.push(("w".to_string(), vec![0.25.into()]));
+            operations.push(("RG".to_string(), vec![0.0.into(), 0.0.into(), 0.0.into()]));
+            for ((x1, y1), (x2, y2)) in layout.crop_marks(page_cards.len()) {
+                operations.push(("m".to_string(), vec![x1.into(), y1.into()]));
+                operations.push(("l".to_string(), vec![x2.into(), y2.into()]));
+                operations.push(("S".to_string(), vec![]));
             }
-            content_data.extend_from_slice(operator.as_bytes());
-            content_data.push(b'\n');
         }
         
+        let content_data = encode_content(operations);
+        
         // Create page
         let content_id = doc.add_object(Stream::new(Dictionary::new(), content_data));
         
@@ -499,6 +1249,14 @@ fn generate_punch_card_pdf(
         xobjects.set(format!("Im{}", image_id.0), Object::Reference(image_id));
         resources.set("XObject", Object::Dictionary(xobjects));
         
+        resources.set("Font", font_resources(&[(interpret::FONT, "Courier"), (interpret::UNPUNCHED_FONT, "Courier-Oblique")]));
+        
+        if options.cut_contour || options.cut_contour_outline {
+            let mut colour_spaces = Dictionary::new();
+            colour_spaces.set(cutter::CUT_CONTOUR, cutter::cut_contour_colour_space());
+            resources.set("ColorSpace", Object::Dictionary(colour_spaces));
+        }
+        
         let mut page_dict = Dictionary::new();
         page_dict.set("Type", Object::Name(b"Page".to_vec()));
         page_dict.set("MediaBox", vec![0.into(), 0.into(), page_width.into(), page_height.into()]);
@@ -508,6 +1266,90 @@ fn generate_punch_card_pdf(
         let _page_id = doc.add_object(page_dict);
     }
     
+    save_pdf(doc, output_path)
+}
+
+/// Write the deck's cutting paths, one file per sheet; with several sheets
+/// each file is numbered after the name given
+fn write_cut_files(
+    deck: &Deck,
+    template_path: &str,
+    vector_card: bool,
+    layout: &Layout,
+    output_path: &str,
+    format: CutFormat,
+    options: &CutOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // The punch grid the PDF's cards are drawn with
+    let vector_card = vector_card || !std::path::Path::new(template_path).exists();
+    let CardTemplate { size: (img_width, img_height), grid, .. } = load_template(template_path, vector_card)?;
+    let image_size = (img_width as f32, img_height as f32);
+    
+    let cards = deck.punch_cards(&get_hollerith_encoding());
+    let sheets = layout.impose(cards.len());
+    let path = std::path::Path::new(output_path);
+    for (sheet_idx, sheet_cards) in sheets.iter().enumerate() {
+        let sheet_cards: Vec<&PunchCard> = sheet_cards.iter().map(|&card_idx| &cards[card_idx]).collect();
+        let paths = cutter::sheet_paths(layout, &sheet_cards, &grid, image_size, options)?;
+        let contents = match format {
+            CutFormat::Svg => cutter::svg(&paths, layout.page_width / PT_PER_MM, layout.page_height / PT_PER_MM),
+            CutFormat::Dxf => cutter::dxf(&paths),
+        };
+        let sheet_path = match sheets.len() {
+            1 => path.to_path_buf(),
+            _ => {
+                let stem = path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+                let extension = path.extension().map_or_else(String::new, |ext| format!(".{}", ext.to_string_lossy()));
+                path.with_file_name(format!("{}-{}{}", stem, sheet_idx + 1, extension))
+            }
+        };
+        fs::write(&sheet_path, contents)?;
+    }
+    println!("✓ Cutting paths written: {} ({:?}, sheets: {})", output_path, format, sheets.len());
+    Ok(())
+}
+
+/// Write a picture of every card into each directory given: SVG at true
+/// size, and PNG at `dpi` dots per inch
+fn write_card_pictures(
+    deck: &Deck,
+    template_path: &str,
+    vector_card: bool,
+    rulings: &[usize],
+    svg_dir: Option<&str>,
+    png_dir: Option<&str>,
+    dpi: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let vector_card = vector_card || !std::path::Path::new(template_path).exists();
+    let CardTemplate { image: img_rgb, size: image_size, grid } = load_template(template_path, vector_card)?;
+    let cards = deck.punch_cards(&get_hollerith_encoding());
+    // Numbered so the files sort in deck order
+    let name = |card_idx: usize, extension: &str| format!("card-{:04}.{}", card_idx + 1, extension);
+    
+    if let Some(dir) = svg_dir {
+        fs::create_dir_all(dir)?;
+        let background = render::svg_background(img_rgb.as_ref(), rulings)?;
+        for (card_idx, card) in cards.iter().enumerate() {
+            fs::write(std::path::Path::new(dir).join(name(card_idx, "svg")), render::svg(card, &background, image_size, &grid))?;
+        }
+        println!("✓ SVG cards written: {} ({} cards)", dir, cards.len());
+    }
+    
+    if let Some(dir) = png_dir {
+        fs::create_dir_all(dir)?;
+        let background = render::png_background(img_rgb.as_ref(), rulings, dpi);
+        for (card_idx, card) in cards.iter().enumerate() {
+            render::png(card, &background, image_size, &grid).save(std::path::Path::new(dir).join(name(card_idx, "png")))?;
+        }
+        println!("✓ PNG cards written: {} ({} cards at {} dpi)", dir, cards.len(), dpi);
+    }
+    Ok(())
+}
+
+/// Give the document its page tree and catalog, then write it out
+fn save_pdf(mut doc: lopdf::Document, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use lopdf::{Dictionary, Object};
+    
     // After all pages are added, build the page tree manually
     let page_ids: Vec<_> = doc.objects.iter()
         .filter(|(_, obj)| {
@@ -535,10 +1377,8 @@ fn generate_punch_card_pdf(
     
     // Update all pages to reference the Pages object as parent
     for page_id in page_ids {
-        if let Ok(page_obj) = doc.get_object_mut(page_id) {
-            if let Object::Dictionary(page_dict) = page_obj {
-                page_dict.set("Parent", Object::Reference(pages_id));
-            }
+        if let Ok(Object::Dictionary(page_dict)) = doc.get_object_mut(page_id) {
+            page_dict.set("Parent", Object::Reference(pages_id));
         }
     }
     
@@ -568,10 +1408,8 @@ fn generate_punch_card_pdf(
         });
     
     // Now update the catalog with Pages reference
-    if let Ok(catalog_obj) = doc.get_object_mut(catalog_id) {
-        if let Object::Dictionary(catalog_dict) = catalog_obj {
-            catalog_dict.set("Pages", Object::Reference(pages_id));
-        }
+    if let Ok(Object::Dictionary(catalog_dict)) = doc.get_object_mut(catalog_id) {
+        catalog_dict.set("Pages", Object::Reference(pages_id));
     }
     
     // Save PDF
@@ -580,13 +1418,66 @@ fn generate_punch_card_pdf(
     Ok(())
 }
 
+/// Write the printer alignment page: rulers, and a crosshair on every punch
+/// position of each card position on the page
+fn write_calibration_page(
+    template_path: &str,
+    profile: &PrinterProfile,
+    layout: &Layout,
+    output_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use lopdf::{Dictionary, Document, Object, Stream};
+    
+    let vector_card = !std::path::Path::new(template_path).exists();
+    let CardTemplate { size: (img_width, img_height), grid, .. } = load_template(template_path, vector_card)?;
+    
+    let (page_width, page_height) = (layout.page_width, layout.page_height);
+    let scale_x = layout.card_width / img_width as f32;
+    let scale_y = layout.card_height / img_height as f32;
+    
+    let mut cards = Vec::new();
+    let mut punch_centres = Vec::new();
+    for card_position in 0..layout.cards_per_page {
+        cards.push(layout.card_space(card_position));
+        let [a, b, c, d, e, f] = layout.card_transform(card_position);
+        for col_idx in 0..COLUMNS {
+            for row_idx in 0..ROWS {
+                let x = (grid.punch_left(col_idx) + grid.punch_width / 2.0) * scale_x;
+                let y = (img_height as f32 - grid.punch_top(row_idx) - grid.punch_height / 2.0) * scale_y;
+                punch_centres.push((a * x + c * y + e, b * x + d * y + f));
+            }
+        }
+    }
+    
+    let mut operations: Vec<(String, Vec<Object>)> = profile.page_transform(page_width, page_height).into_iter().collect();
+    operations.extend(printer::alignment_operations(page_width, page_height, &cards, &punch_centres));
+    
+    let mut doc = Document::with_version("1.5");
+    let content_id = doc.add_object(Stream::new(Dictionary::new(), encode_content(operations)));
+    let mut resources = Dictionary::new();
+    resources.set("Font", font_resources(&[(printer::FONT, "Helvetica")]));
+    
+    let mut page_dict = Dictionary::new();
+    page_dict.set("Type", Object::Name(b"Page".to_vec()));
+    page_dict.set("MediaBox", vec![0.into(), 0.into(), page_width.into(), page_height.into()]);
+    page_dict.set("Contents", Object::Reference(content_id));
+    page_dict.set("Resources", Object::Dictionary(resources));
+    doc.add_object(page_dict);
+    
+    save_pdf(doc, output_path)
+}
+
 /// COBOL to Punch Card PDF Generator
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// COBOL source file to process
-    #[arg(short, long)]
-    input: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    
+    /// COBOL source files or directories to process. With several programs
+    /// the first is the main program.
+    #[arg(short, long, num_args = 1.., required = true)]
+    input: Vec<String>,
     
     /// Output PDF file path
     #[arg(short, long, default_value = "output.pdf")]
@@ -603,49 +1494,804 @@ struct Args {
     /// Include JCL (Job Control Language) wrapper
     #[arg(short, long, default_value_t = false)]
     jcl: bool,
+    
+    /// Kind of job to wrap the source in: compile and run it, or load it
+    /// into a dataset named after the program
+    #[arg(long, value_enum, default_value_t = JclKind::Compile, requires = "jcl")]
+    jcl_kind: JclKind,
+    
+    /// With several programs: one job with a compile step per program and
+    /// a shared link-edit, or a separate job per program
+    #[arg(long, value_enum, default_value_t = JobLayout::Combined, requires = "jcl")]
+    job_layout: JobLayout,
+    
+    /// Target system: z/OS with Enterprise COBOL, or MVS 3.8j under Hercules
+    /// (TK4-) with ANS COBOL
+    #[arg(long, value_enum, default_value_t = Dialect::Zos, requires = "jcl")]
+    dialect: Dialect,
+    
+    /// Site profile supplying compiler options and dataset names
+    #[arg(long, value_enum, default_value_t = Profile::Default, requires = "jcl")]
+    profile: Profile,
+    
+    /// Library for the link-edit step to search for called modules outside
+    /// the deck: DSNAME, or DSNAME(MEMBER,...) to list what it provides
+    #[arg(long = "call-library", requires = "jcl")]
+    call_libraries: Vec<CallLibrary>,
+    
+    /// Punch the deck even when a CALL names a module neither the deck nor a
+    /// call library provides
+    #[arg(long, default_value_t = false, requires = "jcl")]
+    allow_unresolved_calls: bool,
+    
+    /// Compile EXEC SQL with the compiler's SQL coprocessor instead of a
+    /// separate DB2 precompile step
+    #[arg(long, default_value_t = false, requires = "jcl")]
+    sql_coprocessor: bool,
+    
+    /// Treat the inputs as data and lay every line out into a card under an
+    /// IBM 029 program (drum) card, written as the text that punches it;
+    /// a line's entries for the keyed fields are separated by tabs, and a
+    /// directory gives its .txt, .dat and .tsv files
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["jcl", "import", "binary", "object"])]
+    drum_card: Option<String>,
+    
+    /// The same source keyed again by a second operator, one file per input
+    /// in the same order; every card is compared column by column against
+    /// the first keying and notched as verified or in error
+    #[arg(long, num_args = 1.., value_name = "FILE")]
+    verify_with: Vec<String>,
+    
+    /// Treat the inputs as card-image files (SIMH .cbn, EBCDIC or ASCII
+    /// records) and print the cards they hold instead of COBOL source
+    #[arg(long, default_value_t = false, conflicts_with_all = ["jcl", "verify_with"])]
+    import: bool,
+    
+    /// Format of the imported card images; by default taken from each file's
+    /// extension, as for --card-image-format
+    #[arg(long, value_enum, requires = "import")]
+    import_format: Option<CardImageFormat>,
+    
+    /// Punch the input file's bytes as column-binary cards, with a checked
+    /// header on every card and a trailer card; see the restore command
+    #[arg(long, default_value_t = false, conflicts_with_all = ["jcl", "import", "verify_with"])]
+    binary: bool,
+    
+    /// Punch an object deck (ESD, TXT, RLD and END cards) from an object
+    /// description: CSECT name, TEXT file, LENGTH, ENTRY and RELOC statements
+    #[arg(long, default_value_t = false, conflicts_with_all = ["jcl", "import", "binary", "verify_with"])]
+    object: bool,
+    
+    /// Print each column's character along the top edge of the card, as the
+    /// 029 keypunch does; characters that cannot be punched print in red
+    /// italics
+    #[arg(long, default_value_t = false)]
+    interpret: bool,
+    
+    /// Print a line in the larger type of an IBM 557 interpreter along card
+    /// row ROW (12, 11 or 0-9), holding columns FIRST-LAST (at most 60;
+    /// 1-60 by default); may be given more than once
+    #[arg(long, value_name = "ROW[:FIRST-LAST]")]
+    interpreter_line: Vec<InterpreterLine>,
+    
+    /// Draw the card as lines and type instead of using the template image;
+    /// also done when the template file is missing
+    #[arg(long, default_value_t = false)]
+    vector_card: bool,
+    
+    /// Rule a line on a drawn card after each of these columns, e.g.
+    /// 6,7,11,72 for the sequence, indicator, A and B areas of COBOL
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..80))]
+    ruling: Vec<usize>,
+    
+    /// Printer profile whose offset, scale and rotation corrections are
+    /// applied to every page; see the calibration-page command
+    #[arg(long, value_name = "FILE")]
+    printer: Option<String>,
+    
+    #[command(flatten)]
+    page_layout: PageLayoutArgs,
+    
+    /// Draw crop marks outside the corners of each card, for cutting the
+    /// sheets apart with a guillotine
+    #[arg(long, default_value_t = false)]
+    crop_marks: bool,
+    
+    /// Draw the punches as hairlines in a `CutContour` spot colour for a
+    /// print shop's cutting table, instead of as black holes
+    #[arg(long, default_value_t = false)]
+    cut_contour: bool,
+    
+    /// Also draw each card's outline in the `CutContour` colour
+    #[arg(long, default_value_t = false)]
+    cut_contour_outline: bool,
+    
+    /// Also write every card's outline and punches as paths for a laser
+    /// cutter or cutting plotter, at true size in mm with the punches where
+    /// the PDF prints them; the cards are set on the cutter's sheet as on
+    /// the pages, one file per sheet
+    #[arg(long, value_name = "PATH")]
+    cut_file: Option<String>,
+    
+    /// Cutting path format; by default taken from the file extension (.dxf
+    /// for DXF, otherwise SVG)
+    #[arg(long, value_enum, requires = "cut_file")]
+    cut_format: Option<CutFormat>,
+    
+    /// Radius of the cut cards' corners in mm, for --cut-file and
+    /// --cut-contour-outline; 0 for square corners
+    #[arg(long, default_value_t = 0.0, value_name = "MM")]
+    corner_radius: f32,
+    
+    /// Width of material the laser or blade takes away, in mm; the paths
+    /// are moved by half of it so the holes and cards come out true to size
+    #[arg(long, default_value_t = 0.0, value_name = "MM", requires = "cut_file")]
+    kerf: f32,
+    
+    /// Also write a picture of every card as SVG, one file per card in this
+    /// directory. Pictures show the card and its punches only, without the
+    /// --interpret printing or verifier notches.
+    #[arg(long, value_name = "DIR")]
+    svg_dir: Option<String>,
+    
+    /// Also write every card as a PNG image, one file per card in this
+    /// directory; like the SVG pictures, without --interpret printing or
+    /// notches
+    #[arg(long, value_name = "DIR")]
+    png_dir: Option<String>,
+    
+    /// Resolution of the PNG cards, in dots per inch
+    #[arg(long, default_value_t = 300, requires = "png_dir", value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(10..=2400))]
+    dpi: u32,
+    
+    /// Also write the deck as a card-image file for an emulator's card reader
+    #[arg(long, value_name = "PATH")]
+    card_image: Option<String>,
+    
+    /// Card-image format; by default taken from the file extension (.cbn
+    /// for SIMH column binary, .ebc for EBCDIC, otherwise ASCII)
+    #[arg(long, value_enum, requires = "card_image")]
+    card_image_format: Option<CardImageFormat>,
+    
+    /// Also feed the deck to a Hercules sockdev card reader at HOST:PORT
+    #[arg(long, value_name = "HOST:PORT")]
+    reader: Option<String>,
+    
+    /// Record format the card reader expects, as set on its device statement
+    #[arg(long, value_enum, default_value_t = ReaderCode::Ascii, requires = "reader")]
+    reader_code: ReaderCode,
+    
+    /// Further attempts to connect while the card reader is busy or not up
+    #[arg(long, default_value_t = 5, requires = "reader")]
+    reader_retries: u32,
+    
+    /// Seconds to wait between connection attempts
+    #[arg(long, default_value_t = 2, requires = "reader")]
+    reader_retry_delay: u64,
+}
+
+/// Page size and how the cards are set on it
+#[derive(clap::Args, Debug)]
+struct PageLayoutArgs {
+    /// Paper: a4, a3, a5, letter, legal, tabloid, card (one card per page at
+    /// the card's exact size), or WIDTHxHEIGHT in mm, or in inches as 8.5x11in
+    #[arg(long, default_value = "a4", value_name = "SIZE")]
+    page_size: PageSize,
+    
+    /// Cards on each page; by default as many as fit
+    #[arg(long, value_name = "N")]
+    cards_per_page: Option<usize>,
+    
+    /// Gap between cards in mm, the cards centred on the page; by default
+    /// the spare space is shared out equally
+    #[arg(long, value_name = "MM")]
+    gutter: Option<f32>,
+    
+    /// Turn the cards a quarter turn, to fit more on some page sizes
+    #[arg(long, default_value_t = false)]
+    rotate_cards: bool,
+    
+    /// Print the card's colour this many mm past its edges, so a cut a
+    /// little off the line leaves no white edge
+    #[arg(long, default_value_t = 0.0, value_name = "MM")]
+    bleed: f32,
+    
+    /// Spread the deck over the sheets so that, once the stack is cut, the
+    /// piles put one on another are in deck order
+    #[arg(long, default_value_t = false)]
+    cut_and_stack: bool,
+}
+
+impl PageLayoutArgs {
+    fn layout(&self) -> Result<Layout, String> {
+        if !self.bleed.is_finite() || self.bleed < 0.0 {
+            return Err(format!("bleed of {} mm must be a distance of 0 or more", self.bleed));
+        }
+        if let Some(gutter) = self.gutter.filter(|gutter| !gutter.is_finite() || *gutter < 0.0) {
+            return Err(format!("gutter of {} mm must be a distance of 0 or more", gutter));
+        }
+        let mut layout = Layout::new(self.page_size, self.cards_per_page, self.gutter, self.bleed, self.rotate_cards)?;
+        layout.cut_and_stack = self.cut_and_stack;
+        Ok(layout)
+    }
+}
+
+// File extensions picked up when an input is a directory
+const COBOL_EXTENSIONS: &[&str] = &["cob", "cbl", "cobol"];
+const CARD_IMAGE_EXTENSIONS: &[&str] = &["cbn", "ebc", "ebcdic", "crd", "dck"];
+const DATA_EXTENSIONS: &[&str] = &["txt", "dat", "tsv"];
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Read scanned card images (PNG or JPEG, one card each) back into text,
+    /// with a confidence score for every column
+    Decode {
+        /// Scanned card images, in deck order
+        #[arg(short, long, num_args = 1.., required = true)]
+        input: Vec<String>,
+        
+        /// Decoded card text, one line per card
+        #[arg(short, long, default_value = "decoded.txt")]
+        output: String,
+        
+        /// Also write every column's reading and confidence as CSV
+        #[arg(long)]
+        confidence: Option<String>,
+        
+        /// Card template whose punch grid the scanned cards follow
+        #[arg(short, long, default_value = "punchcard_template.png")]
+        template: String,
+    },
+    
+    /// Check a generated PDF against what was meant to be punched, reading
+    /// every punch back from the page and comparing column by column
+    Verify {
+        /// Punch card PDF to check
+        #[arg(short, long)]
+        input: String,
+        
+        /// Coding sheet written alongside the PDF
+        #[arg(long, required_unless_present = "source", conflicts_with = "source")]
+        coding_sheet: Option<String>,
+        
+        /// COBOL source the PDF was made from without --jcl (files or directories)
+        #[arg(long, num_args = 1..)]
+        source: Vec<String>,
+        
+        /// Card template the PDF was made with, for its punch grid
+        #[arg(short, long, default_value = "punchcard_template.png")]
+        template: String,
+        
+        /// The PDF was made with --cut-and-stack
+        #[arg(long, default_value_t = false)]
+        cut_and_stack: bool,
+    },
+    
+    /// Key cards one column at a time on a simulated IBM 029 keypunch, with
+    /// DUP, multi-punch and release/feed; the deck is saved as card images
+    Keypunch {
+        /// Card-image deck to save to; if it exists, keying carries on after
+        /// its cards
+        #[arg(short, long, default_value = "keypunch.cbn")]
+        output: String,
+        
+        /// Card-image format; by default taken from the file extension
+        #[arg(long, value_enum)]
+        format: Option<CardImageFormat>,
+        
+        /// Also print the deck as a punch card PDF
+        #[arg(long)]
+        pdf: Option<String>,
+        
+        /// Coding sheet to write alongside the PDF
+        #[arg(short, long, default_value = "coding_sheet.txt", requires = "pdf")]
+        coding_sheet: String,
+        
+        /// Punch card template image for the PDF
+        #[arg(short, long, default_value = "punchcard_template.png", requires = "pdf")]
+        template: String,
+    },
+    
+    /// Measure a new template's punch grid from the digits printed on it and
+    /// write it beside the template, for the other commands to use
+    Calibrate {
+        /// Card template image to measure
+        #[arg(short, long)]
+        template: String,
+        
+        /// Calibration file to write; by default the template's name with a
+        /// .toml extension, where it is looked for
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    
+    /// Print a page of rulers and crosshairs at every card and punch
+    /// position, to measure a printer's corrections for a printer profile
+    CalibrationPage {
+        /// PDF to write
+        #[arg(short, long, default_value = "calibration_page.pdf")]
+        output: String,
+        
+        /// Card template whose punch grid to mark; without the file, the
+        /// grid of a drawn card
+        #[arg(short, long, default_value = "punchcard_template.png")]
+        template: String,
+        
+        /// Printer profile to apply, to check its corrections
+        #[arg(long, value_name = "FILE")]
+        printer: Option<String>,
+        
+        #[command(flatten)]
+        page_layout: PageLayoutArgs,
+    },
+    
+    /// Rebuild a file punched with --binary from its SIMH .cbn card images,
+    /// checking every card's number and CRC and the trailer's length and hash
+    Restore {
+        /// Column-binary card-image deck (.cbn)
+        #[arg(short, long)]
+        input: String,
+        
+        /// File to write the restored data to
+        #[arg(short, long)]
+        output: String,
+    },
+}
+
+/// Carry out a subcommand
+fn run_command(command: &Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Decode { input, output, confidence, template } => {
+            let template_image = image::open(template)?.to_rgb8();
+            let calibration = calibration::load(template)?;
+            let decoding_map = get_hollerith_decoding();
+            let mut text = String::new();
+            let mut csv = String::from("card,column,character,punches,confidence\n");
+            
+            for (idx, path) in input.iter().enumerate() {
+                let card_num = idx + 1;
+                let scanned = scan::read_card(&image::open(path)?.to_rgb8(), &template_image, &calibration)
+                    .map_err(|err| format!("{}: {}", path, err))?;
+                for warning in &scanned.warnings {
+                    eprintln!("Warning: {}: {}", path, warning);
+                }
+                
+                let card = PunchCard { columns: scanned.columns };
+                let line = card.decode(&decoding_map);
+                for (col_idx, ((ch, punches), score)) in line.chars().zip(&card.columns).zip(&scanned.confidence).enumerate() {
+                    if ch == UNMAPPED_COLUMN {
+                        eprintln!("Warning: {}: column {} punched {} is no known character", path, col_idx + 1, punch_pattern(punches));
+                    }
+                    let shown = if ch == '"' { "\"\"\"\"".to_string() } else { format!("\"{}\"", ch) };
+                    csv.push_str(&format!("{},{},{},{},{:.2}\n", card_num, col_idx + 1, shown, punch_pattern(punches), score));
+                }
+                
+                let (low_col, low_score) = scanned.confidence
+                    .iter()
+                    .enumerate()
+                    .min_by(|a, b| a.1.total_cmp(b.1))
+                    .map_or((0, 1.0), |(col_idx, &score)| (col_idx + 1, score));
+                let unsure = scanned.confidence.iter().filter(|&&score| score < 0.5).count();
+                println!("Card {} ({}): lowest confidence {:.2} at column {}{}", card_num, path, low_score, low_col,
+                    if unsure > 0 { format!(", {} uncertain columns", unsure) } else { String::new() });
+                
+                text.push_str(line.trim_end());
+                text.push('\n');
+            }
+            
+            fs::write(output, text)?;
+            println!("✓ Decoded {} cards: {}", input.len(), output);
+            if let Some(path) = confidence {
+                fs::write(path, csv)?;
+                println!("✓ Column confidence written: {}", path);
+            }
+        }
+        Command::Verify { input, coding_sheet, source, template, cut_and_stack } => {
+            let encoding_map = get_hollerith_encoding();
+            let decoding_map = get_hollerith_decoding();
+            println!("Reading punches from PDF: {}", input);
+            let pages = verify::read_pdf(input, &calibration::load(template)?).map_err(|err| format!("{}: {}", input, err))?;
+            let punched = verify::deck_order(pages, *cut_and_stack);
+            
+            let (expected, against) = match coding_sheet {
+                Some(path) => {
+                    let cards = read_coding_sheet(&fs::read_to_string(path)?, &encoding_map)
+                        .map_err(|err| format!("{}: {}", path, err))?;
+                    (cards, path.clone())
+                }
+                None => {
+                    // Numbered the way a deck without JCL is
+                    let mut deck = Deck::new();
+                    for path in collect_inputs(source, COBOL_EXTENSIONS)? {
+                        let lines: Vec<String> = io::BufReader::new(fs::File::open(&path)?).lines().collect::<Result<_, _>>()?;
+                        let formatted_lines = validate_and_format_cobol(lines)?;
+                        deck.push_program(&extract_program_name(&formatted_lines), &formatted_lines, 1);
+                    }
+                    (deck.punch_cards(&encoding_map), source.join(", "))
+                }
+            };
+            
+            let shown = |punches: &[usize]| {
+                let ch = decoding_map.get(punches).copied().unwrap_or(UNMAPPED_COLUMN);
+                match (punches.is_empty(), ch) {
+                    (true, _) => "blank".to_string(),
+                    (false, UNMAPPED_COLUMN) => punch_pattern(punches),
+                    (false, ch) => format!("{} ({})", punch_pattern(punches), ch),
+                }
+            };
+            let mismatches = verify::compare(&expected, &punched);
+            for mismatch in &mismatches {
+                eprintln!(
+                    "Card {} column {}: expected {}, punched {}",
+                    mismatch.card,
+                    mismatch.column,
+                    shown(&mismatch.expected),
+                    shown(&mismatch.punched)
+                );
+            }
+            let cards_off: std::collections::BTreeSet<usize> = mismatches.iter().map(|m| m.card).collect();
+            
+            let mut problems = Vec::new();
+            if expected.len() != punched.len() {
+                problems.push(format!("{} has {} cards but {} lists {}", input, punched.len(), against, expected.len()));
+            }
+            if !mismatches.is_empty() {
+                problems.push(format!("{} columns differ on {} cards", mismatches.len(), cards_off.len()));
+            }
+            if !problems.is_empty() {
+                return Err(format!("Verification failed: {}", problems.join("; ")).into());
+            }
+            println!("✓ Verified {} cards: {} matches {}", punched.len(), input, against);
+        }
+        Command::Keypunch { output, format, pdf, coding_sheet, template } => {
+            let encoding_map = get_hollerith_encoding();
+            let decoding_map = get_hollerith_decoding();
+            let format = format.unwrap_or_else(|| CardImageFormat::from_path(output));
+            
+            let previous = match fs::read(output) {
+                Ok(data) => card_image::read_deck(&data, format, &encoding_map).map_err(|err| format!("{}: {}", output, err))?,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+                Err(err) => return Err(err.into()),
+            };
+            let already = previous.len();
+            let cards = keypunch::run(previous, &encoding_map, &decoding_map)?;
+            
+            let mut deck = Deck::new();
+            deck.push_punched("KEYPUNCH", cards, &decoding_map);
+            if format != CardImageFormat::Cbn && deck.lines.iter().any(|line| line.card_text().contains(UNMAPPED_COLUMN)) {
+                eprintln!("Warning: multi-punched columns that are no character are saved blank; use a .cbn deck to keep them");
+            }
+            let mut file = io::BufWriter::new(fs::File::create(output)?);
+            card_image::write_deck(&mut file, &deck.punch_cards(&encoding_map), &deck.card_images(&encoding_map), format)?;
+            println!("✓ {} cards keyed, {} in the deck: {} ({:?})", deck.lines.len() - already, deck.lines.len(), output, format);
+            
+            if let Some(pdf) = pdf {
+                generate_punch_card_pdf(&deck, template, pdf, coding_sheet, &PdfOptions::default())?;
+                println!("✓ PDF generated: {}", pdf);
+            }
+        }
+        Command::Calibrate { template, output } => {
+            println!("Measuring the punch grid of template: {}", template);
+            let template_image = image::open(template)?.to_rgb8();
+            let calibration = calibration::detect(&template_image).map_err(|err| format!("{}: {}", template, err))?;
+            println!("  First punch:     ({:.1}, {:.1}) px", calibration.first_punch_x, calibration.first_punch_y);
+            println!("  Column spacing:  {:.2} px", calibration.column_spacing);
+            println!("  Row spacing:     {:.2} px", calibration.row_spacing);
+            println!("  Punch size:      {:.1} x {:.1} px", calibration.punch_width, calibration.punch_height);
+            
+            let path = output.clone().unwrap_or_else(|| calibration::sidecar_path(template).display().to_string());
+            fs::write(&path, calibration.to_toml())?;
+            println!("✓ Calibration written: {}", path);
+        }
+        Command::CalibrationPage { output, template, printer, page_layout } => {
+            let profile = match printer {
+                Some(path) => PrinterProfile::load(path)?,
+                None => PrinterProfile::default(),
+            };
+            write_calibration_page(template, &profile, &page_layout.layout()?, output)?;
+            println!("✓ Calibration page generated: {}", output);
+        }
+        Command::Restore { input, output } => {
+            println!("Reading column-binary cards: {}", input);
+            let cards = card_image::read_deck(&fs::read(input)?, CardImageFormat::Cbn, &get_hollerith_encoding())
+                .map_err(|err| format!("{}: {}", input, err))?;
+            let data = binary_deck::decode(&cards).map_err(|err| format!("{}: {}", input, err))?;
+            fs::write(output, &data)?;
+            println!("✓ Restored {} bytes from {} cards: {}", data.len(), cards.len(), output);
+        }
+    }
+    Ok(())
+}
+
+/// Expand directories into the files with the given extensions they
+/// contain, sorted by name
+fn collect_inputs(paths: &[String], extensions: &[&str]) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    
+    for path in paths {
+        if !fs::metadata(path)?.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        
+        let mut found: Vec<String> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+            })
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+    
+    Ok(files)
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
     
+    if let Some(command) = &args.command {
+        return run_command(command);
+    }
+    
     println!("COBOL to Punch Card PDF Generator");
     println!("==================================");
-    println!("Input file:      {}", args.input);
+    println!("Input files:     {}", args.input.join(", "));
     println!("Output PDF:      {}", args.output);
     println!("Coding sheet:    {}", args.coding_sheet);
-    println!("Include JCL:     {}", if args.jcl { "Yes" } else { "No" });
+    println!("Include JCL:     {}", if args.jcl { format!("Yes ({:?})", args.jcl_kind) } else { "No".to_string() });
     println!();
     
-    println!("Reading COBOL file: {}", args.input);
-    let file = fs::File::open(&args.input)?;
-    let reader = io::BufReader::new(file);
-    let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
+    if args.binary {
+        let [input_file] = args.input.as_slice() else {
+            return Err("--binary punches exactly one input file".into());
+        };
+        let data = fs::read(input_file)?;
+        let cards = binary_deck::encode(&data);
+        println!("Punching {} bytes as {} column-binary cards ({} bytes per card and a trailer)", data.len(), cards.len(), binary_deck::PAYLOAD_BYTES);
+        
+        let name = std::path::Path::new(input_file)
+            .file_name()
+            .map_or_else(|| input_file.clone(), |file| file.to_string_lossy().to_uppercase());
+        let mut deck = Deck::new();
+        deck.push_punched(&name, cards, &get_hollerith_decoding());
+        return write_outputs(&args, &deck);
+    }
+    
+    if args.object {
+        let [input_file] = args.input.as_slice() else {
+            return Err("--object takes exactly one object description".into());
+        };
+        let object = object_deck::parse_description(input_file)?;
+        let records = object_deck::build_records(&object);
+        println!("Object deck for {}: {} cards", object.name(), records.len());
+        
+        let mut deck = Deck::new();
+        deck.push_punched(object.name(), object_deck::punch(&records), &get_hollerith_decoding());
+        return write_outputs(&args, &deck);
+    }
+    
+    if let Some(drum_card_path) = &args.drum_card {
+        let encoding_map = get_hollerith_encoding();
+        let program = drum_card::parse(&fs::read_to_string(drum_card_path)?, &encoding_map)
+            .map_err(|err| format!("{}: {}", drum_card_path, err))?;
+        let lay_out = |path: &String| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+            println!("Laying out data: {}", path);
+            Ok(drum_card::lay_out(&program, &fs::read_to_string(path)?, &encoding_map)
+                .map_err(|err| format!("{}: {}", path, err))?)
+        };
+        
+        let input_files = collect_inputs(&args.input, DATA_EXTENSIONS)?;
+        if input_files.is_empty() {
+            return Err("No data files found in the given inputs".into());
+        }
+        let mut deck = Deck::new();
+        for input_file in &input_files {
+            let name = std::path::Path::new(input_file)
+                .file_name()
+                .map_or_else(|| input_file.clone(), |file| file.to_string_lossy().to_uppercase());
+            deck.push_data(&name, lay_out(input_file)?);
+        }
+        println!("Total cards (data): {}", deck.lines.len());
+        
+        if !args.verify_with.is_empty() {
+            let second_files = collect_inputs(&args.verify_with, DATA_EXTENSIONS)?;
+            if second_files.len() != input_files.len() {
+                return Err(format!("--verify-with needs one file per input ({} inputs, {} given)", input_files.len(), second_files.len()).into());
+            }
+            let second_keying = second_files.iter().map(lay_out).collect::<Result<Vec<_>, _>>()?;
+            println!("Verifying against second keying...");
+            verify_double_entry(&mut deck, &second_keying, &encoding_map);
+        }
+        return write_outputs(&args, &deck);
+    }
+    
+    if args.import {
+        let input_files = collect_inputs(&args.input, CARD_IMAGE_EXTENSIONS)?;
+        if input_files.is_empty() {
+            return Err("No card-image files found in the given inputs".into());
+        }
+        let deck = import_deck(&input_files, args.import_format)?;
+        return write_outputs(&args, &deck);
+    }
     
-    println!("Validating and formatting COBOL...");
-    let formatted_lines = validate_and_format_cobol(lines)?;
+    let input_files = collect_inputs(&args.input, COBOL_EXTENSIONS)?;
+    if input_files.is_empty() {
+        return Err("No COBOL source files found in the given inputs".into());
+    }
+    
+    let mut sources = Vec::new();
+    for input_file in &input_files {
+        println!("Reading COBOL file: {}", input_file);
+        let file = fs::File::open(input_file)?;
+        let reader = io::BufReader::new(file);
+        let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
+        
+        println!("Validating and formatting COBOL...");
+        let formatted_lines = validate_and_format_cobol(lines)?;
+        
+        println!("Processing {} lines of COBOL...", formatted_lines.len());
+        sources.push(formatted_lines);
+    }
     
-    println!("Processing {} lines of COBOL...", formatted_lines.len());
+    let mut second_keying = Vec::new();
+    if !args.verify_with.is_empty() {
+        let second_files = collect_inputs(&args.verify_with, COBOL_EXTENSIONS)?;
+        if second_files.len() != input_files.len() {
+            return Err(format!("--verify-with needs one file per input ({} inputs, {} given)", input_files.len(), second_files.len()).into());
+        }
+        for second_file in &second_files {
+            println!("Reading second keying: {}", second_file);
+            let lines: Vec<String> = io::BufReader::new(fs::File::open(second_file)?).lines().collect::<Result<_, _>>()?;
+            second_keying.push(validate_and_format_cobol(lines)?);
+        }
+    }
     
     if args.jcl {
         println!("Generating JCL wrapper...");
     }
     
+    let mut profile = args.profile.settings(args.dialect);
+    profile.sql_coprocessor = args.sql_coprocessor;
+    profile.call_libraries = args.call_libraries.clone();
+    profile.allow_unresolved_calls = args.allow_unresolved_calls;
+    
+    let mut deck = assemble_deck(&sources, args.jcl.then_some(args.jcl_kind), &profile, args.job_layout)?;
+    if !second_keying.is_empty() {
+        println!("Verifying against second keying...");
+        verify_double_entry(&mut deck, &second_keying, &get_hollerith_encoding());
+    }
+    write_outputs(&args, &deck)
+}
+
+/// Produce everything asked for from the finished deck
+fn write_outputs(args: &Args, deck: &Deck) -> Result<(), Box<dyn std::error::Error>> {
+    // Binary cards have no characters to send as text records, and object
+    // decks only survive as EBCDIC
+    let format = args.card_image.as_deref().map(|path| args.card_image_format.unwrap_or_else(|| CardImageFormat::from_path(path)));
+    let reader_format = args.reader.as_ref().map(|_| args.reader_code.record_format());
+    let formats = [format, reader_format];
+    if deck.has_punched(binary_deck::is_binary_card) && formats.iter().flatten().any(|&f| f != CardImageFormat::Cbn) {
+        return Err("Column-binary cards can only be written as SIMH .cbn card images".into());
+    }
+    if deck.has_punched(object_deck::is_object_card) && formats.contains(&Some(CardImageFormat::Ascii)) {
+        return Err("Object decks hold binary fields and can only be written as EBCDIC or .cbn card images".into());
+    }
+    
+    let layout = args.page_layout.layout()?;
     generate_punch_card_pdf(
-        formatted_lines, 
+        deck, 
         &args.template, 
         &args.output, 
         &args.coding_sheet,
-        args.jcl
+        &PdfOptions {
+            interpret: args.interpret,
+            interpreter_lines: args.interpreter_line.clone(),
+            vector_card: args.vector_card,
+            rulings: args.ruling.clone(),
+            printer: match &args.printer {
+                Some(path) => PrinterProfile::load(path)?,
+                None => PrinterProfile::default(),
+            },
+            layout: layout.clone(),
+            crop_marks: args.crop_marks,
+            cut_contour: args.cut_contour,
+            cut_contour_outline: args.cut_contour_outline,
+            corner_radius_mm: args.corner_radius,
+        },
     )?;
     
+    if let Some(path) = &args.cut_file {
+        let format = args.cut_format.unwrap_or_else(|| CutFormat::from_path(path));
+        let options = CutOptions { corner_radius_mm: args.corner_radius, kerf_mm: args.kerf };
+        write_cut_files(deck, &args.template, args.vector_card, &layout, path, format, &options)?;
+    }
+    
+    if args.svg_dir.is_some() || args.png_dir.is_some() {
+        write_card_pictures(deck, &args.template, args.vector_card, &args.ruling, args.svg_dir.as_deref(), args.png_dir.as_deref(), args.dpi)?;
+    }
+    
+    if let (Some(path), Some(format)) = (&args.card_image, format) {
+        let encoding_map = get_hollerith_encoding();
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        card_image::write_deck(&mut file, &deck.punch_cards(&encoding_map), &deck.card_images(&encoding_map), format)?;
+        println!("✓ Card images written: {} ({:?})", path, format);
+    }
+    
+    if let Some(address) = &args.reader {
+        let connection = ReaderConnection {
+            address: address.clone(),
+            code: args.reader_code,
+            retries: args.reader_retries,
+            retry_delay: Duration::from_secs(args.reader_retry_delay),
+        };
+        let encoding_map = get_hollerith_encoding();
+        let records = card_image::records(
+            &deck.punch_cards(&encoding_map),
+            &deck.card_images(&encoding_map),
+            args.reader_code.record_format(),
+        );
+        println!("Sending {} cards to card reader at {}...", records.len(), address);
+        sockdev::send_deck(&connection, &records)
+            .map_err(|err| format!("Card reader at {}: {}", address, err))?;
+        println!("✓ Deck read in by card reader at {}", address);
+    }
+    
     println!();
     println!("✓ Punch cards generated successfully!");
     println!("  PDF:           {}", args.output);
     println!("  Coding sheet:  {}", args.coding_sheet);
+    if let Some(path) = &args.card_image {
+        println!("  Card images:   {}", path);
+    }
+    if let Some(address) = &args.reader {
+        println!("  Card reader:   {} ({:?})", address, args.reader_code);
+    }
     
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_keying_notches_each_card() {
+        let encoding_map = get_hollerith_encoding();
+        let first = ["       MOVE A TO B.", "       ADD 1 TO C.", "       STOP RUN."];
+        let mut deck = Deck::new();
+        deck.push_program("PARTS", &first.map(String::from), 1);
+        let second = vec!["       MOVE A TO B.".to_string(), "       ADD 7 TO D.".to_string()];
+        verify_double_entry(&mut deck, &[second], &encoding_map);
+
+        assert!(matches!(deck.verification[&0], Verification::Matched));
+        let Verification::Differed(columns) = &deck.verification[&1] else { panic!("keyings differ") };
+        assert_eq!(columns, &[12, 17]);
+        // The card the second operator never keyed is left alone
+        assert!(matches!(deck.verification[&2], Verification::Missing));
+
+        // The OK notch is cut into the right end at row 1, error notches
+        // into the top edge over their columns
+        let grid = Calibration::BUNDLED;
+        let image_width = vector_card::WIDTH_PX;
+        let ok = deck.verification[&0].notches(&grid, image_width);
+        let middle = grid.punch_top(3) + grid.punch_height / 2.0;
+        assert_eq!(ok, [[
+            (image_width, middle - NOTCH_HALF_WIDTH_PX),
+            (image_width - NOTCH_DEPTH_PX, middle),
+            (image_width, middle + NOTCH_HALF_WIDTH_PX),
+        ]]);
+        let errors = deck.verification[&1].notches(&grid, image_width);
+        let apexes: Vec<(f32, f32)> = errors.iter().map(|notch| notch[1]).collect();
+        let over = |col: usize| grid.punch_left(col - 1) + grid.punch_width / 2.0;
+        assert_eq!(apexes, [(over(12), NOTCH_DEPTH_PX), (over(17), NOTCH_DEPTH_PX)]);
+        assert!(deck.verification[&2].notches(&grid, image_width).is_empty());
+    }
+}
+
 // Cargo.toml dependencies needed:
 // [dependencies]
 // lopdf = "0.32"
@@ -0,0 +1,420 @@
+// OS/360 object decks, as the assembler and compilers punched them on
+// SYSPUNCH: ESD, TXT, RLD and END cards, each with X'02' (12-2-9) in
+// column 1 and binary fields punched in full EBCDIC card code.
+//
+// The object is described in a small text file, one statement per line.
+// A `*` at the start of a line or after a space starts a comment:
+//
+//   CSECT  HELLO             control section name
+//   TEXT   hello.bin         file holding the section's bytes
+//   LENGTH 0200              section length in hex (default: text length)
+//   ENTRY  0000              entry point offset in hex (optional)
+//   RELOC  0010 A(HELLO)     adcon at offset X'10' relocated by HELLO
+//   RELOC  0014 V(PRINTIT)   adcon resolved to external symbol PRINTIT
+//   RELOC  0018 AL3(HELLO)   three-byte adcon
+
+use std::fs;
+use std::path::Path;
+
+use crate::card_image::{column_bits, column_punches};
+use crate::{ebcdic, PunchCard, COLUMNS};
+
+/// Column 1 of every object deck card
+const RECORD_MARK: u8 = 0x02;
+/// Bytes of data an ESD, TXT or RLD card carries in columns 17-72
+const DATA_BYTES: usize = 56;
+/// Largest address a 24-bit field holds
+const MAX_ADDRESS: u32 = 0xFF_FFFF;
+
+// ESD item types
+const ESD_SD: u8 = 0x00;
+const ESD_ER: u8 = 0x02;
+
+/// Kind of address constant a relocation fixes up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AdconKind {
+    /// A-type: address of a symbol
+    Address,
+    /// V-type: address of an external routine
+    External,
+}
+
+/// An address constant in the text the linkage editor must relocate
+#[derive(Debug)]
+struct Relocation {
+    offset: u32,
+    length: u8,
+    kind: AdconKind,
+    symbol: String,
+}
+
+/// An object module with one control section
+#[derive(Debug)]
+pub struct ObjectDescription {
+    csect: String,
+    length: u32,
+    text: Vec<u8>,
+    entry: Option<u32>,
+    relocations: Vec<Relocation>,
+}
+
+impl ObjectDescription {
+    /// Control section name
+    pub fn name(&self) -> &str {
+        &self.csect
+    }
+}
+
+/// Check a name is valid for an external symbol: up to 8 characters, a
+/// letter or national character first
+fn symbol_name(text: &str, line_num: usize) -> Result<String, String> {
+    let name = text.to_uppercase();
+    let valid_char = |ch: char| ch.is_ascii_alphanumeric() || matches!(ch, '$' | '#' | '@');
+    let starts_well = name.chars().next().is_some_and(|ch| ch.is_ascii_alphabetic() || matches!(ch, '$' | '#' | '@'));
+    if name.len() > 8 || !starts_well || !name.chars().all(valid_char) {
+        return Err(format!("Line {}: {} is not a valid external symbol", line_num, text));
+    }
+    Ok(name)
+}
+
+/// Parse a hex address
+fn address(text: &str, line_num: usize) -> Result<u32, String> {
+    let value = u32::from_str_radix(text.trim_start_matches("X'").trim_end_matches('\''), 16)
+        .map_err(|_| format!("Line {}: {} is not a hex address", line_num, text))?;
+    if value > MAX_ADDRESS {
+        return Err(format!("Line {}: {} does not fit in 24 bits", line_num, text));
+    }
+    Ok(value)
+}
+
+/// Parse an address constant in assembler form: A(SYM), V(SYM), AL3(SYM)
+fn adcon(text: &str, line_num: usize) -> Result<(AdconKind, u8, String), String> {
+    let upper = text.to_uppercase();
+    let invalid = || format!("Line {}: {} is not an A(...) or V(...) address constant", line_num, text);
+
+    let (kind, rest) = match upper.chars().next() {
+        Some('A') => (AdconKind::Address, &upper[1..]),
+        Some('V') => (AdconKind::External, &upper[1..]),
+        _ => return Err(invalid()),
+    };
+    let (length, rest) = match rest.strip_prefix('L') {
+        Some(rest) => {
+            let digits = rest.find('(').ok_or_else(invalid)?;
+            (rest[..digits].parse::<u8>().map_err(|_| invalid())?, &rest[digits..])
+        }
+        None => (4, rest),
+    };
+    if !(1..=4).contains(&length) {
+        return Err(format!("Line {}: address constants are 1 to 4 bytes long", line_num));
+    }
+    let symbol = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')).ok_or_else(invalid)?;
+    Ok((kind, length, symbol_name(symbol, line_num)?))
+}
+
+/// A description line without its comment: a `*` at the start of the line
+/// or after a space, so a `*` inside an operand is kept
+fn strip_comment(line: &str) -> &str {
+    let mut prev = ' ';
+    for (idx, ch) in line.char_indices() {
+        if ch == '*' && prev.is_whitespace() {
+            return &line[..idx];
+        }
+        prev = ch;
+    }
+    line
+}
+
+/// Read an object description; the text file is found relative to it
+pub fn parse_description(path: &str) -> Result<ObjectDescription, String> {
+    let source = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    let base_dir = Path::new(path).parent().unwrap_or(Path::new("."));
+
+    let mut csect = None;
+    let mut text = None;
+    let mut length = None;
+    let mut entry = None;
+    let mut relocations = Vec::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        let line_num = idx + 1;
+        let line = strip_comment(line).trim();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let operands: Vec<&str> = words.collect();
+        let operand = |n: usize| {
+            operands
+                .get(n)
+                .copied()
+                .ok_or_else(|| format!("Line {}: {} needs {} operand(s)", line_num, keyword, n + 1))
+        };
+
+        match keyword.to_uppercase().as_str() {
+            "CSECT" => csect = Some(symbol_name(operand(0)?, line_num)?),
+            "TEXT" => {
+                let text_path = base_dir.join(operand(0)?);
+                let bytes = fs::read(&text_path).map_err(|err| format!("{}: {}", text_path.display(), err))?;
+                text = Some(bytes);
+            }
+            "LENGTH" => length = Some(address(operand(0)?, line_num)?),
+            "ENTRY" => entry = Some(address(operand(0)?, line_num)?),
+            "RELOC" => {
+                let offset = address(operand(0)?, line_num)?;
+                let (kind, length, symbol) = adcon(operand(1)?, line_num)?;
+                relocations.push(Relocation { offset, length, kind, symbol });
+            }
+            other => return Err(format!("Line {}: unknown statement {}", line_num, other)),
+        }
+    }
+
+    let csect = csect.ok_or("no CSECT statement naming the control section")?;
+    let text = text.unwrap_or_default();
+    let length = length.unwrap_or(text.len() as u32);
+    if text.len() > length as usize || length > MAX_ADDRESS {
+        return Err(format!(
+            "{} bytes of text do not fit a control section of X'{:X}' bytes",
+            text.len(),
+            length
+        ));
+    }
+    if let Some(entry) = entry.filter(|&entry| entry >= length.max(1)) {
+        return Err(format!("entry point X'{:X}' lies outside the control section", entry));
+    }
+    for relocation in &relocations {
+        if relocation.offset + relocation.length as u32 > length {
+            return Err(format!("address constant at X'{:X}' lies outside the control section", relocation.offset));
+        }
+        if relocation.kind == AdconKind::External && relocation.symbol == csect {
+            return Err(format!("V({}) refers to the section itself; use A({})", csect, csect));
+        }
+    }
+
+    Ok(ObjectDescription { csect, length, text, entry, relocations })
+}
+
+/// Three-byte big-endian field
+fn address_bytes(value: u32) -> [u8; 3] {
+    let bytes = value.to_be_bytes();
+    [bytes[1], bytes[2], bytes[3]]
+}
+
+/// Name padded with blanks to 8 EBCDIC characters
+fn name_bytes(name: &str) -> Vec<u8> {
+    ebcdic::encode(&format!("{:<8}", name))
+}
+
+/// Start an 80-byte record: the 12-2-9 mark, the record type, blanks
+fn new_record(record_type: &str) -> Vec<u8> {
+    let mut record = vec![ebcdic::SPACE; COLUMNS];
+    record[0] = RECORD_MARK;
+    record[1..4].copy_from_slice(&ebcdic::encode(record_type));
+    record
+}
+
+/// Set the binary fields common to ESD, TXT and RLD records: the byte count
+/// in columns 11-12 and the data in columns 17 onwards
+fn set_data(record: &mut [u8], data: &[u8]) {
+    record[10..12].copy_from_slice(&(data.len() as u16).to_be_bytes());
+    record[16..16 + data.len()].copy_from_slice(data);
+}
+
+/// Build the deck's records: ESD, TXT, RLD, then END. Columns 73-80 carry
+/// the deck ID and sequence number, as the assembler punched them.
+pub fn build_records(object: &ObjectDescription) -> Vec<Vec<u8>> {
+    let mut records = Vec::new();
+
+    // ESD: the section itself is ESDID 1, each external symbol follows
+    let mut externals: Vec<&str> = Vec::new();
+    for relocation in &object.relocations {
+        if relocation.symbol != object.csect && !externals.contains(&relocation.symbol.as_str()) {
+            externals.push(&relocation.symbol);
+        }
+    }
+    let esdid = |symbol: &str| -> u16 {
+        if symbol == object.csect {
+            1
+        } else {
+            externals.iter().position(|&name| name == symbol).unwrap() as u16 + 2
+        }
+    };
+
+    let mut items = Vec::new();
+    let mut sd = name_bytes(&object.csect);
+    sd.push(ESD_SD);
+    sd.extend_from_slice(&[0, 0, 0]);
+    sd.push(0x00);
+    sd.extend_from_slice(&address_bytes(object.length));
+    items.push(sd);
+    for name in &externals {
+        let mut er = name_bytes(name);
+        er.push(ESD_ER);
+        er.extend_from_slice(&[0, 0, 0]);
+        er.extend_from_slice(&[ebcdic::SPACE; 4]);
+        items.push(er);
+    }
+    for (chunk_idx, chunk) in items.chunks(3).enumerate() {
+        let mut record = new_record("ESD");
+        set_data(&mut record, &chunk.concat());
+        record[14..16].copy_from_slice(&(chunk_idx as u16 * 3 + 1).to_be_bytes());
+        records.push(record);
+    }
+
+    // TXT: the section's bytes, 56 to a card, with their address
+    for (chunk_idx, chunk) in object.text.chunks(DATA_BYTES).enumerate() {
+        let mut record = new_record("TXT");
+        record[5..8].copy_from_slice(&address_bytes((chunk_idx * DATA_BYTES) as u32));
+        set_data(&mut record, chunk);
+        record[14..16].copy_from_slice(&1u16.to_be_bytes());
+        records.push(record);
+    }
+
+    // RLD: full entries carry the R (target) and P (section) pointers;
+    // following entries with the same pointers leave them out, which the
+    // previous entry announces in its flag byte
+    let mut relocations: Vec<&Relocation> = object.relocations.iter().collect();
+    relocations.sort_by_key(|r| (esdid(&r.symbol), r.offset));
+    let mut data: Vec<u8> = Vec::new();
+    let mut last_flag: Option<usize> = None;
+    let mut last_target = None;
+    for relocation in relocations {
+        let target = esdid(&relocation.symbol);
+        let kind_bits = match relocation.kind {
+            AdconKind::Address => 0x00,
+            AdconKind::External => 0x10,
+        };
+        let flag = kind_bits | (relocation.length - 1) << 2;
+
+        let continues = last_target == Some(target);
+        let entry_len = if continues { 4 } else { 8 };
+        if data.len() + entry_len > DATA_BYTES {
+            let mut record = new_record("RLD");
+            set_data(&mut record, &data);
+            records.push(record);
+            data.clear();
+            last_flag = None;
+        }
+
+        if data.is_empty() || !continues {
+            data.extend_from_slice(&target.to_be_bytes());
+            data.extend_from_slice(&1u16.to_be_bytes());
+        } else if let Some(pos) = last_flag {
+            data[pos] |= 0x01;
+        }
+        last_flag = Some(data.len());
+        data.push(flag);
+        data.extend_from_slice(&address_bytes(relocation.offset));
+        last_target = Some(target);
+    }
+    if !data.is_empty() {
+        let mut record = new_record("RLD");
+        set_data(&mut record, &data);
+        records.push(record);
+    }
+
+    // END: the entry point, if one was given
+    let mut record = new_record("END");
+    if let Some(entry) = object.entry {
+        record[5..8].copy_from_slice(&address_bytes(entry));
+        record[14..16].copy_from_slice(&1u16.to_be_bytes());
+    }
+    records.push(record);
+
+    // Deck ID from the section name, then the sequence number
+    let deck_id: String = format!("{:<4}", object.csect).chars().take(4).collect();
+    for (idx, record) in records.iter_mut().enumerate() {
+        record[72..].copy_from_slice(&ebcdic::encode(&format!("{}{:04}", deck_id, idx + 1)));
+    }
+
+    records
+}
+
+/// Whether a card is an object deck card, going by the 12-2-9 punch in
+/// column 1
+pub fn is_object_card(columns: &[Vec<usize>]) -> bool {
+    columns.first().is_some_and(|punches| column_bits(punches) == ebcdic::card_code(RECORD_MARK))
+}
+
+/// Punch records with the EBCDIC card code, byte for column
+pub fn punch(records: &[Vec<u8>]) -> Vec<PunchCard> {
+    records
+        .iter()
+        .map(|record| PunchCard {
+            columns: record
+                .iter()
+                .map(|&byte| column_punches(ebcdic::card_code(byte)))
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comments_start_a_line_or_follow_a_space() {
+        assert_eq!(strip_comment("* a comment line"), "");
+        assert_eq!(strip_comment("CSECT HELLO   * the program"), "CSECT HELLO   ");
+        assert_eq!(strip_comment("TEXT a*b.bin"), "TEXT a*b.bin");
+        assert_eq!(strip_comment("TEXT a*b.bin\t* its text"), "TEXT a*b.bin\t");
+        assert_eq!(strip_comment("LENGTH 100"), "LENGTH 100");
+    }
+
+    #[test]
+    fn records_follow_the_object_deck_layout() {
+        let object = ObjectDescription {
+            csect: "HELLO".to_string(),
+            length: 0x100,
+            text: (0..60).collect(),
+            entry: Some(0x10),
+            relocations: vec![
+                Relocation { offset: 0x14, length: 4, kind: AdconKind::External, symbol: "PRINTIT".to_string() },
+                Relocation { offset: 0x10, length: 4, kind: AdconKind::Address, symbol: "HELLO".to_string() },
+            ],
+        };
+        let records = build_records(&object);
+        let types: Vec<Vec<u8>> = records.iter().map(|record| record[1..4].to_vec()).collect();
+        assert_eq!(types, ["ESD", "TXT", "TXT", "RLD", "END"].map(ebcdic::encode));
+        for (idx, record) in records.iter().enumerate() {
+            assert_eq!(record.len(), COLUMNS);
+            assert_eq!(record[0], RECORD_MARK);
+            assert_eq!(record[72..], ebcdic::encode(&format!("HELL{:04}", idx + 1)));
+        }
+
+        // ESD: the section and the external symbol, 16 bytes each
+        let esd = &records[0];
+        assert_eq!(esd[10..12], [0, 32]);
+        assert_eq!(esd[14..16], [0, 1]);
+        assert_eq!(esd[16..24], ebcdic::encode("HELLO   "));
+        assert_eq!(esd[24..32], [ESD_SD, 0, 0, 0, 0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(esd[32..40], ebcdic::encode("PRINTIT "));
+        assert_eq!(esd[40], ESD_ER);
+
+        // TXT: the address in columns 6-8, the byte count in 11-12
+        let (first, second) = (&records[1], &records[2]);
+        assert_eq!((first[5..8].to_vec(), first[10..12].to_vec()), (vec![0, 0, 0], vec![0, 56]));
+        assert_eq!(first[16..72], (0..56).collect::<Vec<u8>>()[..]);
+        assert_eq!((second[5..8].to_vec(), second[10..12].to_vec()), (vec![0, 0, 56], vec![0, 4]));
+        assert_eq!(second[16..20], [56, 57, 58, 59]);
+        assert_eq!(second[20], ebcdic::SPACE);
+
+        // RLD: the section's own adcon first, then the external one
+        let rld = &records[3];
+        assert_eq!(rld[10..12], [0, 16]);
+        assert_eq!(rld[16..32], [0, 1, 0, 1, 0x0C, 0, 0, 0x10, 0, 2, 0, 1, 0x1C, 0, 0, 0x14]);
+
+        // END: the entry point
+        assert_eq!(records[4][5..8], [0, 0, 0x10]);
+
+        // Punched byte for column in card code, 12-2-9 in column 1
+        let cards = punch(&records);
+        assert!(cards.iter().all(|card| is_object_card(&card.columns)));
+        assert_eq!(cards[0].columns[0], [0, 4, 11]);
+        for (record, card) in records.iter().zip(&cards) {
+            for (&byte, punches) in record.iter().zip(&card.columns) {
+                assert_eq!(column_bits(punches), ebcdic::card_code(byte));
+            }
+        }
+    }
+}
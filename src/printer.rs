@@ -0,0 +1,187 @@
+// Correcting for a particular printer. Printers shift, stretch and skew
+// their output a little, which matters when cards are printed onto real
+// card stock whose digits are already there. A printer profile holds the
+// corrections, measured from the alignment page that `calibration-page`
+// prints, in a file of the same form as a template's calibration:
+//
+//   offset_x_mm = 0.4     moves the print right (negative: left)
+//   offset_y_mm = -0.6    moves the print up (negative: down)
+//   scale_x = 1.002       stretches the print across
+//   scale_y = 0.998       stretches the print from top to bottom
+//   rotation_deg = 0.1    turns the print anticlockwise
+//
+// Keys left out make no correction. Scaling and rotation are about the
+// middle of the page.
+
+use lopdf::Object;
+
+use crate::{calibration, PT_PER_MM};
+
+/// Font resource name for the alignment page's figures (Helvetica)
+pub const FONT: &str = "F1";
+
+/// Rulers stop this far from the paper's edges, which most printers cannot
+/// reach
+const RULER_MARGIN_MM: f32 = 8.0;
+
+/// One printer's corrections
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrinterProfile {
+    pub offset_x_mm: f32,
+    pub offset_y_mm: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub rotation_deg: f32,
+}
+
+impl Default for PrinterProfile {
+    fn default() -> Self {
+        PrinterProfile { offset_x_mm: 0.0, offset_y_mm: 0.0, scale_x: 1.0, scale_y: 1.0, rotation_deg: 0.0 }
+    }
+}
+
+impl PrinterProfile {
+    /// Read a profile file
+    pub fn parse(text: &str) -> Result<PrinterProfile, String> {
+        let mut profile = PrinterProfile::default();
+        for (line_num, key, value) in calibration::key_values(text)? {
+            let field = match key {
+                "offset_x_mm" => &mut profile.offset_x_mm,
+                "offset_y_mm" => &mut profile.offset_y_mm,
+                "scale_x" => &mut profile.scale_x,
+                "scale_y" => &mut profile.scale_y,
+                "rotation_deg" => &mut profile.rotation_deg,
+                other => return Err(format!("line {}: unknown key {}", line_num, other)),
+            };
+            *field = value;
+        }
+        if profile.scale_x <= 0.0 || profile.scale_y <= 0.0 {
+            return Err("scales must be greater than 0".to_string());
+        }
+        Ok(profile)
+    }
+
+    /// Read a profile from its file
+    pub fn load(path: &str) -> Result<PrinterProfile, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        PrinterProfile::parse(&text).map_err(|err| format!("{}: {}", path, err))
+    }
+
+    /// The correction as a `cm` operation for the whole of a page, or none
+    /// when the profile corrects nothing
+    pub fn page_transform(&self, page_width: f32, page_height: f32) -> Option<(String, Vec<Object>)> {
+        if *self == PrinterProfile::default() {
+            return None;
+        }
+        let (sin, cos) = self.rotation_deg.to_radians().sin_cos();
+        let (a, b) = (self.scale_x * cos, self.scale_x * sin);
+        let (c, d) = (-self.scale_y * sin, self.scale_y * cos);
+        let (cx, cy) = (page_width / 2.0, page_height / 2.0);
+        let e = cx + self.offset_x_mm * PT_PER_MM - (a * cx + c * cy);
+        let f = cy + self.offset_y_mm * PT_PER_MM - (b * cx + d * cy);
+        Some(("cm".to_string(), [a, b, c, d, e, f].iter().map(|&n| n.into()).collect()))
+    }
+}
+
+fn operation(operator: &str, operands: &[f32]) -> (String, Vec<Object>) {
+    (operator.to_string(), operands.iter().map(|&n| n.into()).collect())
+}
+
+fn line(operations: &mut Vec<(String, Vec<Object>)>, from: (f32, f32), to: (f32, f32)) {
+    operations.push(operation("m", &[from.0, from.1]));
+    operations.push(operation("l", &[to.0, to.1]));
+    operations.push(operation("S", &[]));
+}
+
+fn label(operations: &mut Vec<(String, Vec<Object>)>, text: &str, size: f32, (x, y): (f32, f32)) {
+    operations.push(("BT".to_string(), vec![]));
+    operations.push(("Tf".to_string(), vec![Object::Name(FONT.as_bytes().to_vec()), size.into()]));
+    operations.push(operation("Td", &[x, y]));
+    operations.push(("Tj".to_string(), vec![Object::string_literal(text)]));
+    operations.push(("ET".to_string(), vec![]));
+}
+
+/// A crosshair of the given arm length in points
+fn crosshair(operations: &mut Vec<(String, Vec<Object>)>, (x, y): (f32, f32), arm: f32) {
+    line(operations, (x - arm, y), (x + arm, y));
+    line(operations, (x, y - arm), (x, y + arm));
+}
+
+/// Operations for the alignment page, in page points: millimetre rulers
+/// along the top and left edges, numbered from the paper's left and bottom
+/// edges, a crosshair at the middle of the page, and each card's outline
+/// (x, y, width, height) with a crosshair on every punch position
+pub fn alignment_operations(
+    page_width: f32,
+    page_height: f32,
+    cards: &[(f32, f32, f32, f32)],
+    punch_centres: &[(f32, f32)],
+) -> Vec<(String, Vec<Object>)> {
+    let mut operations = vec![operation("w", &[0.2]), operation("RG", &[0.0, 0.0, 0.0]), operation("rg", &[0.0, 0.0, 0.0])];
+    let mm = PT_PER_MM;
+
+    // Rulers: a tick every millimetre, longer every 5 and numbered every 10
+    let tick = |n: usize| match n {
+        n if n % 10 == 0 => 4.0 * mm,
+        n if n % 5 == 0 => 2.5 * mm,
+        _ => 1.5 * mm,
+    };
+    let top = page_height - RULER_MARGIN_MM * mm;
+    let left = RULER_MARGIN_MM * mm;
+    for n in RULER_MARGIN_MM as usize..=((page_width / mm) - RULER_MARGIN_MM) as usize {
+        let x = n as f32 * mm;
+        line(&mut operations, (x, top), (x, top - tick(n)));
+        if n % 10 == 0 {
+            label(&mut operations, &n.to_string(), 6.0, (x + 0.5, top - 4.0 * mm));
+        }
+    }
+    for n in RULER_MARGIN_MM as usize..=((page_height / mm) - RULER_MARGIN_MM) as usize {
+        let y = n as f32 * mm;
+        line(&mut operations, (left, y), (left + tick(n), y));
+        if n % 10 == 0 {
+            label(&mut operations, &n.to_string(), 6.0, (left + 4.5 * mm, y + 0.5));
+        }
+    }
+
+    // The middle of the page, for the offsets
+    let middle = (page_width / 2.0, page_height / 2.0);
+    crosshair(&mut operations, middle, 10.0 * mm);
+    label(
+        &mut operations,
+        &format!("page middle ({:.1}, {:.1}) mm", middle.0 / mm, middle.1 / mm),
+        6.0,
+        (middle.0 + 1.0 * mm, middle.1 + 1.0 * mm),
+    );
+
+    for (card_idx, &(x, y, width, height)) in cards.iter().enumerate() {
+        operations.push(operation("re", &[x, y, width, height]));
+        operations.push(operation("S", &[]));
+        label(&mut operations, &format!("Card {}", card_idx + 1), 7.0, (x, y + height + 1.0 * mm));
+    }
+    for &centre in punch_centres {
+        crosshair(&mut operations, centre, 0.6 * mm);
+    }
+
+    label(
+        &mut operations,
+        "Measure the rulers from the paper's edges; put the corrections in a printer profile for --printer",
+        7.0,
+        (left + 10.0 * mm, RULER_MARGIN_MM * mm),
+    );
+    operations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_parse() {
+        let profile = PrinterProfile::parse("# Office laser\noffset_x_mm = 0.4\nscale_y = 0.998  # measured\n\n").unwrap();
+        assert_eq!(profile, PrinterProfile { offset_x_mm: 0.4, scale_y: 0.998, ..PrinterProfile::default() });
+        assert!(PrinterProfile::parse("offset_x_mm = -0.6").is_ok());
+        assert!(PrinterProfile::parse("offset = 1").is_err());
+        assert!(PrinterProfile::parse("scale_x = 0").is_err());
+        assert!(PrinterProfile::parse("rotation_deg = inf").is_err());
+    }
+}
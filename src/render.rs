@@ -0,0 +1,144 @@
+// Pictures of single cards, for putting in documents: an SVG or a PNG of
+// each card, punched through the same grid as the PDF. The SVG carries the
+// template image inside it, or the drawn card when there is no template.
+// The PNG is the template scaled to the chosen resolution, or the drawn
+// card drawn at it, with the holes filled in. Pictures show the punches
+// only: the 029 and 557 printing and the verifier's notches are left to
+// the PDF.
+
+use std::io::Cursor;
+
+use image::imageops::{self, FilterType};
+use image::{ImageOutputFormat, Rgb, RgbImage};
+
+use crate::calibration::Calibration;
+use crate::{vector_card, PunchCard, CARD_HEIGHT_MM, CARD_WIDTH_MM};
+
+const MM_PER_INCH: f32 = 25.4;
+
+/// Base64, for the template image inside each SVG
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let triple = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                text.push(ALPHABET[(triple >> (18 - 6 * idx) & 0x3f) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// What every SVG card is drawn on: the template image, or the drawn card
+/// with its rulings
+pub fn svg_background(template: Option<&RgbImage>, rulings: &[usize]) -> Result<String, String> {
+    let Some(template) = template else {
+        return Ok(vector_card::card_svg(rulings));
+    };
+    let mut png = Vec::new();
+    template
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .map_err(|err| format!("cannot encode the template: {}", err))?;
+    let (width, height) = template.dimensions();
+    Ok(format!(
+        "<image width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\" href=\"data:image/png;base64,{}\"/>\n",
+        width,
+        height,
+        base64(&png)
+    ))
+}
+
+/// A card as SVG at its true size, drawn in template pixels on the given
+/// background
+pub fn svg(card: &PunchCard, background: &str, image_size: (u32, u32), grid: &Calibration) -> String {
+    let (width, height) = image_size;
+    let mut svg = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}mm\" height=\"{}mm\" viewBox=\"0 0 {} {}\">\n",
+        CARD_WIDTH_MM, CARD_HEIGHT_MM, width, height
+    );
+    svg.push_str(background);
+    svg.push_str("<g fill=\"#000000\">\n");
+    for (col_idx, punches) in card.columns.iter().enumerate() {
+        for &row in punches {
+            svg.push_str(&format!(
+                "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\"/>\n",
+                grid.punch_left(col_idx),
+                grid.punch_top(row),
+                grid.punch_width,
+                grid.punch_height
+            ));
+        }
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// The unpunched card at a resolution in dots per inch: the template
+/// scaled, or the drawn card with its rulings
+pub fn png_background(template: Option<&RgbImage>, rulings: &[usize], dpi: u32) -> RgbImage {
+    let dots = |mm: f32| (mm / MM_PER_INCH * dpi as f32).round().max(1.0) as u32;
+    let (width, height) = (dots(CARD_WIDTH_MM), dots(CARD_HEIGHT_MM));
+    match template {
+        Some(template) => imageops::resize(template, width, height, FilterType::Triangle),
+        None => vector_card::card_image(rulings, width, height),
+    }
+}
+
+/// A card as a PNG image: the background with the punches filled in black,
+/// scaled from template pixels
+pub fn png(card: &PunchCard, background: &RgbImage, image_size: (u32, u32), grid: &Calibration) -> RgbImage {
+    let mut image = background.clone();
+    let (width, height) = image.dimensions();
+    let (scale_x, scale_y) = (width as f32 / image_size.0 as f32, height as f32 / image_size.1 as f32);
+    for (col_idx, punches) in card.columns.iter().enumerate() {
+        for &row in punches {
+            let left = (grid.punch_left(col_idx) * scale_x).round() as u32;
+            let right = ((grid.punch_left(col_idx) + grid.punch_width) * scale_x).round() as u32;
+            let top = (grid.punch_top(row) * scale_y).round() as u32;
+            let bottom = ((grid.punch_top(row) + grid.punch_height) * scale_y).round() as u32;
+            for y in top..bottom.min(height) {
+                for x in left..right.min(width) {
+                    image.put_pixel(x, y, Rgb([0, 0, 0]));
+                }
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_pads_to_whole_quads() {
+        let encoded: Vec<String> = ["", "f", "fo", "foo", "foob", "fooba", "foobar"]
+            .iter()
+            .map(|text| base64(text.as_bytes()))
+            .collect();
+        assert_eq!(encoded, ["", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy"]);
+    }
+
+    #[test]
+    fn svg_punches_sit_on_the_grid() {
+        let mut card = PunchCard::new();
+        card.columns[9] = vec![4];
+        let grid = Calibration::BUNDLED;
+        let svg = svg(&card, "", (800, 355), &grid);
+        let rect = format!(
+            "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\"/>",
+            grid.first_punch_x + 9.0 * grid.column_spacing,
+            grid.first_punch_y + 4.0 * grid.row_spacing,
+            grid.punch_width,
+            grid.punch_height
+        );
+        assert!(svg.contains(&rect), "{}", svg);
+        assert_eq!(svg.matches("<rect ").count(), 1);
+    }
+}
@@ -0,0 +1,499 @@
+// Reading punches back from scanned cards. The card is found against the
+// scanner background by its edges, turned upright by its corner cut, and
+// the template's punch grid is laid over it to test every punch position.
+//
+// Holes are told by the backing showing through them: its colour is
+// measured where the template has no print, and each punch position is
+// compared with it and with the card around the position, so uneven
+// lighting and scanner colour shifts do not matter. The backing has to
+// differ from the card stock; print as dark as the backing is no help in
+// telling a hole, so a dark backing suits cards with light print.
+
+use image::{Rgb, RgbImage};
+
+use crate::calibration::Calibration;
+use crate::{COLUMNS, ROWS};
+
+/// Colour distance beyond which a pixel no longer counts as card stock
+const CARD_TOLERANCE: f32 = 80.0;
+/// Fraction of a punch position's points that must look like the backing
+/// to count as a hole
+const HOLE_FRACTION: f32 = 0.5;
+
+/// Punches read from one card, with how sure the reading is
+pub struct ScannedCard {
+    /// Rows punched in each column
+    pub columns: Vec<Vec<usize>>,
+    /// Confidence per column, from 0 (a guess) to 1 (clear-cut)
+    pub confidence: Vec<f32>,
+    /// Problems with locating the card
+    pub warnings: Vec<String>,
+}
+
+/// A point in image pixels
+#[derive(Clone, Copy, Debug)]
+struct Point {
+    x: f32,
+    y: f32,
+}
+
+/// The card's corners in the image, as it lies upright
+struct CardQuad {
+    top_left: Point,
+    top_right: Point,
+    bottom_left: Point,
+    bottom_right: Point,
+}
+
+impl CardQuad {
+    /// Image position of a point given as fractions of the card's width
+    /// and height from its top-left corner
+    fn map(&self, u: f32, v: f32) -> Point {
+        let lerp = |a: Point, b: Point, t: f32| Point {
+            x: a.x + (b.x - a.x) * t,
+            y: a.y + (b.y - a.y) * t,
+        };
+        lerp(lerp(self.top_left, self.top_right, u), lerp(self.bottom_left, self.bottom_right, u), v)
+    }
+
+    /// The same card turned half a turn
+    fn rotated(self) -> Self {
+        CardQuad {
+            top_left: self.bottom_right,
+            top_right: self.bottom_left,
+            bottom_left: self.top_right,
+            bottom_right: self.top_left,
+        }
+    }
+
+    /// The same card seen from the back, columns running right to left
+    fn mirrored(self) -> Self {
+        CardQuad {
+            top_left: self.top_right,
+            top_right: self.top_left,
+            bottom_left: self.bottom_right,
+            bottom_right: self.bottom_left,
+        }
+    }
+}
+
+fn distance(a: Rgb<u8>, b: Rgb<u8>) -> f32 {
+    a.0.iter()
+        .zip(b.0.iter())
+        .map(|(&p, &q)| (p as f32 - q as f32).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Per-channel median of a set of colours
+fn median_colour(mut pixels: Vec<Rgb<u8>>) -> Rgb<u8> {
+    let mut colour = [0u8; 3];
+    for (channel, value) in colour.iter_mut().enumerate() {
+        pixels.sort_by_key(|p| p.0[channel]);
+        *value = pixels[pixels.len() / 2].0[channel];
+    }
+    Rgb(colour)
+}
+
+fn pixel_at(img: &RgbImage, point: Point) -> Rgb<u8> {
+    let x = (point.x.round().max(0.0) as u32).min(img.width() - 1);
+    let y = (point.y.round().max(0.0) as u32).min(img.height() - 1);
+    *img.get_pixel(x, y)
+}
+
+/// Least-squares line `b = slope * a + offset` through (a, b) samples,
+/// refitted once without the samples furthest off it (corner cut, dust)
+fn fit_line(samples: &[(f32, f32)]) -> Option<(f32, f32)> {
+    let fit = |points: &[(f32, f32)]| -> Option<(f32, f32)> {
+        let n = points.len() as f32;
+        if n < 2.0 {
+            return None;
+        }
+        let mean_a = points.iter().map(|p| p.0).sum::<f32>() / n;
+        let mean_b = points.iter().map(|p| p.1).sum::<f32>() / n;
+        let var = points.iter().map(|p| (p.0 - mean_a).powi(2)).sum::<f32>();
+        if var == 0.0 {
+            return None;
+        }
+        let slope = points.iter().map(|p| (p.0 - mean_a) * (p.1 - mean_b)).sum::<f32>() / var;
+        Some((slope, mean_b - slope * mean_a))
+    };
+
+    let (slope, offset) = fit(samples)?;
+    let mut residuals: Vec<f32> = samples.iter().map(|p| (p.1 - (slope * p.0 + offset)).abs()).collect();
+    residuals.sort_by(f32::total_cmp);
+    let cutoff = residuals[residuals.len() * 3 / 4].max(1.0) * 2.0;
+    let kept: Vec<(f32, f32)> = samples
+        .iter()
+        .copied()
+        .filter(|p| (p.1 - (slope * p.0 + offset)).abs() <= cutoff)
+        .collect();
+    fit(&kept).or(Some((slope, offset)))
+}
+
+/// Where lines `y = a1 x + b1` and `x = a2 y + b2` cross
+fn intersect(horizontal: (f32, f32), vertical: (f32, f32)) -> Point {
+    let (a1, b1) = horizontal;
+    let (a2, b2) = vertical;
+    let y = (a1 * b2 + b1) / (1.0 - a1 * a2);
+    Point { x: a2 * y + b2, y }
+}
+
+/// Find the card's edges against the background and return its corners as
+/// found in the image, before orientation. Pixels further than `tolerance`
+/// from the background colour belong to the card.
+fn find_edges(img: &RgbImage, background: Rgb<u8>, tolerance: f32) -> Option<CardQuad> {
+    let (width, height) = img.dimensions();
+    let is_card = |x: u32, y: u32| distance(*img.get_pixel(x, y), background) > tolerance;
+
+    // A card edge is the first run of card pixels met from outside
+    const RUN: u32 = 3;
+    let first_run = |positions: &mut dyn Iterator<Item = (u32, u32)>| -> Option<(u32, u32)> {
+        let mut run = 0;
+        for (x, y) in positions {
+            if is_card(x, y) {
+                run += 1;
+                if run == RUN {
+                    return Some((x, y));
+                }
+            } else {
+                run = 0;
+            }
+        }
+        None
+    };
+
+    // The card's extent along the image's centre lines, so that only the
+    // middle of each edge is sampled, clear of the corners and corner cut
+    let (mid_x, mid_y) = (width / 2, height / 2);
+    let (first_x, _) = first_run(&mut (0..width).map(|x| (x, mid_y)))?;
+    let (last_x, _) = first_run(&mut (0..width).rev().map(|x| (x, mid_y)))?;
+    let (_, first_y) = first_run(&mut (0..height).map(|y| (mid_x, y)))?;
+    let (_, last_y) = first_run(&mut (0..height).rev().map(|y| (mid_x, y)))?;
+    let (span_x, span_y) = (last_x.saturating_sub(first_x), last_y.saturating_sub(first_y));
+
+    let (mut top, mut bottom, mut left, mut right) = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for x in (first_x + span_x / 5..last_x - span_x / 5).step_by(2) {
+        if let Some((_, y)) = first_run(&mut (0..height).map(|y| (x, y))) {
+            top.push((x as f32, y as f32 - (RUN - 1) as f32));
+        }
+        if let Some((_, y)) = first_run(&mut (0..height).rev().map(|y| (x, y))) {
+            bottom.push((x as f32, y as f32 + (RUN - 1) as f32));
+        }
+    }
+    for y in (first_y + span_y / 5..last_y - span_y / 5).step_by(2) {
+        if let Some((x, _)) = first_run(&mut (0..width).map(|x| (x, y))) {
+            left.push((y as f32, x as f32 - (RUN - 1) as f32));
+        }
+        if let Some((x, _)) = first_run(&mut (0..width).rev().map(|x| (x, y))) {
+            right.push((y as f32, x as f32 + (RUN - 1) as f32));
+        }
+    }
+
+    let (top, bottom) = (fit_line(&top)?, fit_line(&bottom)?);
+    let (left, right) = (fit_line(&left)?, fit_line(&right)?);
+    Some(CardQuad {
+        top_left: intersect(top, left),
+        top_right: intersect(top, right),
+        bottom_left: intersect(bottom, left),
+        bottom_right: intersect(bottom, right),
+    })
+}
+
+/// A card found in an image
+struct LocatedCard {
+    /// Corners, with the corner cut at top left
+    quad: CardQuad,
+    /// Colour of the card stock
+    colour: Rgb<u8>,
+    warnings: Vec<String>,
+}
+
+/// Find the card in an image and turn it upright
+fn locate_card(img: &RgbImage) -> Result<LocatedCard, String> {
+    let (width, height) = img.dimensions();
+    if width < 16 || height < 16 {
+        return Err("image is too small to hold a card".to_string());
+    }
+    let mut warnings = Vec::new();
+
+    // The background shows around the image border, the card in the middle
+    let mut border = Vec::new();
+    for x in (0..width).step_by(2) {
+        border.push(*img.get_pixel(x, 0));
+        border.push(*img.get_pixel(x, height - 1));
+    }
+    for y in (0..height).step_by(2) {
+        border.push(*img.get_pixel(0, y));
+        border.push(*img.get_pixel(width - 1, y));
+    }
+    let background = median_colour(border);
+    let mut middle = Vec::new();
+    for y in height * 2 / 5..height * 3 / 5 {
+        for x in (width * 2 / 5..width * 3 / 5).step_by(2) {
+            middle.push(*img.get_pixel(x, y));
+        }
+    }
+    let contrast = distance(background, median_colour(middle));
+
+    // Edges lie halfway between background and card; with nothing to tell
+    // them apart the card has been cropped to fill the image
+    let quad = (contrast > 20.0)
+        .then(|| find_edges(img, background, contrast / 2.0))
+        .flatten()
+        .unwrap_or_else(|| {
+            warnings.push("no background around the card; taking the whole image as the card".to_string());
+            let (w, h) = ((width - 1) as f32, (height - 1) as f32);
+            CardQuad {
+                top_left: Point { x: 0.0, y: 0.0 },
+                top_right: Point { x: w, y: 0.0 },
+                bottom_left: Point { x: 0.0, y: h },
+                bottom_right: Point { x: w, y: h },
+            }
+        });
+
+    // A card on its side: turn it so the long edges run across
+    let across = (quad.top_right.x - quad.top_left.x).hypot(quad.top_right.y - quad.top_left.y);
+    let down = (quad.bottom_left.x - quad.top_left.x).hypot(quad.bottom_left.y - quad.top_left.y);
+    let quad = if down > across {
+        CardQuad {
+            top_left: quad.bottom_left,
+            top_right: quad.top_left,
+            bottom_left: quad.bottom_right,
+            bottom_right: quad.top_right,
+        }
+    } else {
+        quad
+    };
+
+    // The card stock colour, from all over the card
+    let mut stock = Vec::new();
+    for i in 1..20 {
+        for j in 1..20 {
+            stock.push(pixel_at(img, quad.map(i as f32 / 20.0, j as f32 / 20.0)));
+        }
+    }
+    let colour = median_colour(stock);
+
+    // The corner cut shows background where the card's corner would be
+    let corner_is_cut = |u: f32, v: f32| distance(pixel_at(img, quad.map(u, v)), colour) > CARD_TOLERANCE / 2.0;
+    let cut = [(0.01, 0.03), (0.99, 0.03), (0.01, 0.97), (0.99, 0.97)].map(|(u, v)| corner_is_cut(u, v));
+    let quad = match cut {
+        [true, false, false, false] => quad,
+        [false, false, false, true] => quad.rotated(),
+        [false, true, false, false] => {
+            warnings.push("corner cut at top right; reading the card as seen from the back".to_string());
+            quad.mirrored()
+        }
+        [false, false, true, false] => {
+            warnings.push("corner cut at bottom left; reading the card as seen from the back, upside down".to_string());
+            quad.mirrored().rotated()
+        }
+        _ => {
+            warnings.push("no single corner cut found; assuming the card is upright".to_string());
+            quad
+        }
+    };
+
+    Ok(LocatedCard { quad, colour, warnings })
+}
+
+/// Read every column of a scanned card. The punch grid is measured on the
+/// template, so it is laid over the scan relative to the card's edges as
+/// found on both.
+pub fn read_card(img: &RgbImage, template: &RgbImage, grid: &Calibration) -> Result<ScannedCard, String> {
+    let card = locate_card(img)?;
+    let template_card = locate_card(template).map_err(|err| format!("template: {}", err))?;
+
+    // The template is a straight image, so its card is taken as square on
+    let frame = &template_card.quad;
+    let scan_point = |x: f32, y: f32| {
+        let u = (x - frame.top_left.x) / (frame.top_right.x - frame.top_left.x);
+        let v = (y - frame.top_left.y) / (frame.bottom_left.y - frame.top_left.y);
+        pixel_at(img, card.quad.map(u, v))
+    };
+    let is_ink = |pixel: Rgb<u8>, stock: Rgb<u8>| distance(pixel, stock) > CARD_TOLERANCE / 2.0;
+    // Edges blur differently on every scan, so the grid is registered more
+    // closely by lining up the printed digits in each quarter of the card
+    let half_columns = COLUMNS / 2;
+    let half_rows = ROWS / 2;
+    let mut offsets = [[(0.0f32, 0.0f32); 2]; 2];
+    for (quarter_row, row_offsets) in offsets.iter_mut().enumerate() {
+        for (quarter_col, offset) in row_offsets.iter_mut().enumerate() {
+            let mut ink = Vec::new();
+            for col_idx in quarter_col * half_columns..(quarter_col + 1) * half_columns {
+                for row_idx in quarter_row * half_rows..(quarter_row + 1) * half_rows {
+                    let left = grid.punch_left(col_idx);
+                    let top = grid.punch_top(row_idx);
+                    for y in top as u32..(top + grid.punch_height) as u32 {
+                        for x in left as u32..(left + grid.punch_width) as u32 {
+                            let (x, y) = (x.min(template.width() - 1), y.min(template.height() - 1));
+                            ink.push((x as f32, y as f32, is_ink(*template.get_pixel(x, y), template_card.colour)));
+                        }
+                    }
+                }
+            }
+
+            let mut best = 0;
+            for step_y in -8..=8 {
+                for step_x in -8..=8 {
+                    let (dx, dy) = (step_x as f32 / 2.0, step_y as f32 / 2.0);
+                    let agreeing = ink
+                        .iter()
+                        .filter(|&&(x, y, template_ink)| {
+                            template_ink && is_ink(scan_point(x + dx, y + dy), card.colour)
+                        })
+                        .count();
+                    if agreeing > best {
+                        best = agreeing;
+                        *offset = (dx, dy);
+                    }
+                }
+            }
+        }
+    }
+
+    // Offsets hold at the middle of each quarter and are interpolated between
+    let grid_offset = |col: f32, row: f32| {
+        let s = ((col - half_columns as f32 / 2.0) / half_columns as f32).clamp(0.0, 1.0);
+        let t = ((row - half_rows as f32 / 2.0) / half_rows as f32).clamp(0.0, 1.0);
+        let lerp = |a: (f32, f32), b: (f32, f32), k: f32| (a.0 + (b.0 - a.0) * k, a.1 + (b.1 - a.1) * k);
+        lerp(lerp(offsets[0][0], offsets[0][1], s), lerp(offsets[1][0], offsets[1][1], s), t)
+    };
+
+    // The card around each punch position, in the gaps above and below
+    // it: lighting and scanner colour vary across a card, and the gaps
+    // vary with them
+    let gap = (grid.row_spacing - grid.punch_height) / 2.0;
+    let local_stock = |col_idx: usize, row_idx: usize| {
+        let (left, top) = (grid.punch_left(col_idx), grid.punch_top(row_idx));
+        let (dx, dy) = grid_offset(col_idx as f32, row_idx as f32);
+        let mut around = Vec::new();
+        for i in 0..5 {
+            let x = left + grid.punch_width * i as f32 / 4.0;
+            around.push(scan_point(x + dx, top - gap + dy));
+            around.push(scan_point(x + dx, top + grid.punch_height + gap + dy));
+        }
+        median_colour(around)
+    };
+    // Points in each punch position, away from its rim, with the template's
+    // colours around each point taken to the scan's local card colour
+    let points = |col_idx: usize, row_idx: usize| {
+        let (left, top) = (grid.punch_left(col_idx), grid.punch_top(row_idx));
+        let (dx, dy) = grid_offset(col_idx as f32, row_idx as f32);
+        let stock = local_stock(col_idx, row_idx);
+        let to_scan_colour = |pixel: Rgb<u8>| {
+            Rgb(std::array::from_fn(|channel| {
+                let shift = stock.0[channel] as i16 - template_card.colour.0[channel] as i16;
+                (pixel.0[channel] as i16 + shift).clamp(0, 255) as u8
+            }))
+        };
+        let mut points = Vec::new();
+        for i in 0..5 {
+            for j in 0..7 {
+                let x = left + grid.punch_width * (0.25 + 0.5 * i as f32 / 4.0);
+                let y = top + grid.punch_height * (0.15 + 0.7 * j as f32 / 6.0);
+                let expected: Vec<Rgb<u8>> = (-1..=1)
+                    .flat_map(|j| (-1..=1).map(move |i| (i, j)))
+                    .map(|(i, j)| to_scan_colour(pixel_at(template, Point { x: x + i as f32, y: y + j as f32 })))
+                    .collect();
+                points.push((scan_point(x + dx, y + dy), stock, expected));
+            }
+        }
+        points
+    };
+
+    // What shows through the holes, measured where the template has no
+    // print and the scan differs from the card
+    let positions: Vec<Vec<_>> = (0..COLUMNS)
+        .map(|col_idx| (0..ROWS).map(|row_idx| points(col_idx, row_idx)).collect())
+        .collect();
+    let mut through = Vec::new();
+    for (seen, stock, expected) in positions.iter().flatten().flatten() {
+        if expected.iter().all(|&colour| !is_ink(colour, *stock)) && is_ink(*seen, *stock) {
+            through.push(*seen);
+        }
+    }
+    let hole_colour = (!through.is_empty()).then(|| median_colour(through));
+
+    // A point is a hole where the scan is nearer the hole colour than
+    // anything the template shows around it. Print as dark as the holes
+    // tells nothing, so those points are left out.
+    let mut columns = Vec::with_capacity(COLUMNS);
+    let mut confidence = Vec::with_capacity(COLUMNS);
+    for column in &positions {
+        let mut punches = Vec::new();
+        let mut column_confidence = 1.0f32;
+        for (row_idx, points) in column.iter().enumerate() {
+            let mut differing = 0;
+            let mut total = 0;
+            if let Some(hole_colour) = hole_colour {
+                for (seen, _, expected) in points {
+                    if expected.iter().any(|&colour| !is_ink(colour, hole_colour)) {
+                        continue;
+                    }
+                    let nearest = expected.iter().map(|&colour| distance(*seen, colour)).fold(f32::MAX, f32::min);
+                    if distance(*seen, hole_colour) < nearest {
+                        differing += 1;
+                    }
+                    total += 1;
+                }
+            }
+
+            let fraction = differing as f32 / total.max(1) as f32;
+            if fraction >= HOLE_FRACTION {
+                punches.push(row_idx);
+            }
+            column_confidence = column_confidence.min(((fraction - HOLE_FRACTION).abs() / 0.4).min(1.0));
+        }
+        columns.push(punches);
+        confidence.push(column_confidence);
+    }
+
+    Ok(ScannedCard { columns, confidence, warnings: card.warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_hollerith_encoding, PunchCard};
+
+    /// The bundled template with a card's holes cut through to a dark
+    /// backing, with what it was punched from
+    fn punched() -> (RgbImage, RgbImage, Calibration, PunchCard) {
+        let template = image::open("punchcard_template.png").unwrap().to_rgb8();
+        let grid = crate::calibration::load("punchcard_template.png").unwrap();
+        let card = PunchCard::from_control_line("//HELLO JOB (ACCT),'A B',CLASS=A 0123456789 &$*.,;:=+-/", &get_hollerith_encoding());
+        let mut image = template.clone();
+        for (col_idx, punches) in card.columns.iter().enumerate() {
+            for &row_idx in punches {
+                let (left, top) = (grid.punch_left(col_idx), grid.punch_top(row_idx));
+                for y in top.round() as u32..(top + grid.punch_height).round() as u32 {
+                    for x in left.round() as u32..(left + grid.punch_width).round() as u32 {
+                        image.put_pixel(x, y, Rgb([0, 0, 0]));
+                    }
+                }
+            }
+        }
+        (image, template, grid, card)
+    }
+
+    #[test]
+    fn punched_cards_decode() {
+        let (image, template, grid, card) = punched();
+        let scanned = read_card(&image, &template, &grid).unwrap();
+        assert_eq!(scanned.columns, card.columns);
+        let lowest = scanned.confidence.iter().copied().fold(1.0, f32::min);
+        assert!(lowest > 0.9, "confidence {:.2}", lowest);
+    }
+
+    #[test]
+    fn holes_are_found_on_a_dim_scan() {
+        let (mut image, template, grid, card) = punched();
+        for pixel in image.pixels_mut() {
+            pixel.0 = pixel.0.map(|channel| (channel as f32 * 0.75) as u8 + 20);
+        }
+        let scanned = read_card(&image, &template, &grid).unwrap();
+        assert_eq!(scanned.columns, card.columns);
+    }
+}
@@ -0,0 +1,151 @@
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use clap::ValueEnum;
+
+use crate::card_image::CardImageFormat;
+
+/// How card images travel to the reader, matching the `ascii` or `ebcdic`
+/// option on the Hercules sockdev device statement
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReaderCode {
+    /// One line of text per card; Hercules pads it to 80 columns and
+    /// translates it to EBCDIC
+    Ascii,
+    /// Fixed 80-byte EBCDIC records with no separators
+    Ebcdic,
+}
+
+impl ReaderCode {
+    /// Card-image records the reader takes
+    pub fn record_format(self) -> CardImageFormat {
+        match self {
+            ReaderCode::Ascii => CardImageFormat::Ascii,
+            ReaderCode::Ebcdic => CardImageFormat::Ebcdic,
+        }
+    }
+}
+
+/// Where and how to connect to a Hercules socket card reader, e.g.
+/// `000C 3505 3505 sockdev ascii trunc eof` in the Hercules configuration
+pub struct ReaderConnection {
+    /// `host:port` the reader listens on
+    pub address: String,
+    pub code: ReaderCode,
+    /// Further connection attempts after the first one fails
+    pub retries: u32,
+    pub retry_delay: Duration,
+}
+
+/// Write 80-byte card records (see `ReaderCode::record_format`) to any
+/// byte sink the way the reader expects them
+pub fn write_deck<W: Write>(writer: &mut W, records: &[Vec<u8>], code: ReaderCode) -> io::Result<()> {
+    for record in records {
+        match code {
+            ReaderCode::Ascii => {
+                // Hercules pads short lines, so trailing blanks need not be sent
+                let len = record.iter().rposition(|&byte| byte != b' ').map_or(0, |pos| pos + 1);
+                writer.write_all(&record[..len])?;
+                writer.write_all(b"\n")?;
+            }
+            ReaderCode::Ebcdic => writer.write_all(record)?,
+        }
+    }
+    writer.flush()
+}
+
+/// Connect to the reader, retrying while Hercules refuses the connection:
+/// the reader only takes one client at a time and only while it is idle
+fn connect(connection: &ReaderConnection) -> io::Result<TcpStream> {
+    let mut attempt = 0;
+    loop {
+        match TcpStream::connect(&connection.address) {
+            Ok(stream) => return Ok(stream),
+            Err(err) if attempt < connection.retries => {
+                attempt += 1;
+                eprintln!(
+                    "Warning: card reader at {} not ready ({}), retrying {}/{}",
+                    connection.address, err, attempt, connection.retries
+                );
+                thread::sleep(connection.retry_delay);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Feed the deck into a Hercules sockdev card reader. Closing the
+/// connection afterwards is what tells the reader the deck has ended; with
+/// the `eof` device option Hercules then presents end-of-file to the job.
+pub fn send_deck(connection: &ReaderConnection, records: &[Vec<u8>]) -> io::Result<()> {
+    let mut stream = connect(connection)?;
+    write_deck(&mut stream, records, connection.code)?;
+    stream.shutdown(Shutdown::Write)?;
+
+    // Hercules closes its side once it has read everything; wait for that
+    // so the process does not exit with cards still in flight
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut buf = [0u8; 64];
+    loop {
+        match io::Read::read(&mut stream, &mut buf) {
+            Ok(0) => break,
+            Ok(_) => continue,
+            Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset => break,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::{card_image, ebcdic, get_hollerith_encoding, Deck, DeckLine};
+
+    /// Send a deck of JCL cards to a listener standing in for the reader,
+    /// returning what it received
+    fn received(code: ReaderCode) -> (Vec<u8>, Vec<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let reader = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut bytes = Vec::new();
+            stream.read_to_end(&mut bytes).unwrap();
+            bytes
+        });
+
+        let mut deck = Deck::new();
+        for line in ["//LINK     EXEC PGM=IEWL", "//SYSLIN   DD DSN=&&LOADSET,DISP=(OLD,DELETE)", "/*"] {
+            deck.lines.push(DeckLine::Control(line.to_string()));
+        }
+        let encoding_map = get_hollerith_encoding();
+        let images = deck.card_images(&encoding_map);
+        let records = card_image::records(&deck.punch_cards(&encoding_map), &images, code.record_format());
+        let connection = ReaderConnection { address, code, retries: 0, retry_delay: Duration::ZERO };
+        send_deck(&connection, &records).unwrap();
+        (reader.join().unwrap(), images)
+    }
+
+    #[test]
+    fn ascii_reader_gets_lines() {
+        let (bytes, _) = received(ReaderCode::Ascii);
+        let expected = "//LINK     EXEC PGM=IEWL\n//SYSLIN   DD DSN=&&LOADSET,DISP=(OLD,DELETE)\n/*\n";
+        assert_eq!(String::from_utf8(bytes).unwrap(), expected);
+    }
+
+    #[test]
+    fn ebcdic_reader_gets_records() {
+        let (bytes, images) = received(ReaderCode::Ebcdic);
+        let expected: Vec<u8> = images.iter().flat_map(|text| ebcdic::encode(text)).collect();
+        assert_eq!(bytes.len(), 3 * 80);
+        assert_eq!(bytes, expected);
+        // The ampersands of the temporary dataset name arrive punched
+        assert!(bytes[80..160].windows(2).any(|pair| pair == [0x50, 0x50]));
+    }
+}
@@ -0,0 +1,344 @@
+// A card drawn from paths and text instead of the template image, so no
+// template file is needed and the print is sharp at any resolution. It is
+// laid out in the template's pixel units, on a card the template's size,
+// so the punch grid constants place the punches on it unchanged: the
+// outline with its corner cut, the digits 0 to 9 printed in every column
+// of their rows, the column numbers under rows 0 and 9, and rulings
+// between fields. The same card can be had as SVG elements or as an image,
+// for the pictures of single cards; the image has no type to set its
+// figures in, so it draws them from a 5 by 7 dot face.
+
+use image::{Rgb, RgbImage};
+use lopdf::Object;
+
+use crate::{COLUMNS, COLUMN_SPACING, FIRST_PUNCH_X, FIRST_PUNCH_Y, PUNCH_HEIGHT_PX, PUNCH_WIDTH_PX, ROWS, ROW_SPACING};
+
+/// Card size in template pixels
+pub const WIDTH_PX: f32 = 800.0;
+pub const HEIGHT_PX: f32 = 355.0;
+
+/// Font resource name for the card's printing (Helvetica)
+pub const FONT: &str = "F1";
+
+/// The corner cut at top left, along the top edge and down the left edge
+pub const CORNER_CUT_PX: (f32, f32) = (27.0, 16.0);
+/// Type sizes, in template pixels
+const DIGIT_SIZE: f32 = 8.0;
+const COLUMN_NUMBER_SIZE: f32 = 4.0;
+/// Helvetica figures are 556/1000 of the type size wide and about 700/1000
+/// high
+const FIGURE_WIDTH: f32 = 0.556;
+const FIGURE_HEIGHT: f32 = 0.7;
+
+/// Manila card stock and the ink printed on it
+pub const CARD_COLOUR: [f32; 3] = [0.97, 0.94, 0.82];
+const INK_COLOUR: [f32; 3] = [0.25, 0.2, 0.15];
+
+/// The figures 0 to 9 in 5 by 7 dots, a row of dots to a byte with the
+/// leftmost in the top bit
+const FIGURE_DOTS: [[u8; 7]; 10] = [
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110],
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110],
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111],
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110],
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010],
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110],
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110],
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000],
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110],
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100],
+];
+
+fn operation(operator: &str, operands: &[f32]) -> (String, Vec<Object>) {
+    (operator.to_string(), operands.iter().map(|&n| n.into()).collect())
+}
+
+/// Form coordinates run bottom up, template pixels top down
+fn flip(y_px: f32) -> f32 {
+    HEIGHT_PX - y_px
+}
+
+/// Centre of a punch position in template pixels
+fn punch_centre(col_idx: usize, row: usize) -> (f32, f32) {
+    (
+        FIRST_PUNCH_X + col_idx as f32 * COLUMN_SPACING + PUNCH_WIDTH_PX / 2.0,
+        FIRST_PUNCH_Y + row as f32 * ROW_SPACING + PUNCH_HEIGHT_PX / 2.0,
+    )
+}
+
+/// Figures centred on a point given in template pixels
+fn figures(text: &str, size: f32, (x_px, y_px): (f32, f32)) -> [(String, Vec<Object>); 2] {
+    let width = text.len() as f32 * FIGURE_WIDTH * size;
+    let baseline = y_px + FIGURE_HEIGHT * size / 2.0;
+    [
+        operation("Tm", &[1.0, 0.0, 0.0, 1.0, x_px - width / 2.0, flip(baseline)]),
+        ("Tj".to_string(), vec![Object::string_literal(text)]),
+    ]
+}
+
+/// One part of the blank card, in template pixels from the top left
+enum Mark {
+    /// The outline, a closed path filled in the card colour
+    Outline(Vec<(f32, f32)>),
+    /// A field ruling from one point to another
+    Ruling((f32, f32), (f32, f32)),
+    /// Figures centred on a point, at a type size
+    Figures(String, f32, (f32, f32)),
+}
+
+/// Line widths of the outline and rulings, in template pixels
+const OUTLINE_WIDTH: f32 = 0.75;
+const RULING_WIDTH: f32 = 0.5;
+
+/// What is drawn on the blank card, the figures last; `rulings` are the
+/// columns (1-based) to rule a line after
+fn card_marks(rulings: &[usize]) -> Vec<Mark> {
+    // Outline, starting along the top from the corner cut
+    let (cut_x, cut_y) = CORNER_CUT_PX;
+    let mut marks = vec![Mark::Outline(vec![(cut_x, 0.0), (WIDTH_PX, 0.0), (WIDTH_PX, HEIGHT_PX), (0.0, HEIGHT_PX), (0.0, cut_y)])];
+
+    // Field rulings, from the top of the 12 row to the bottom of the 9 row
+    let top = FIRST_PUNCH_Y - 4.0;
+    let bottom = FIRST_PUNCH_Y + (ROWS - 1) as f32 * ROW_SPACING + PUNCH_HEIGHT_PX + 4.0;
+    for &column in rulings {
+        let x = FIRST_PUNCH_X + column as f32 * COLUMN_SPACING - (COLUMN_SPACING - PUNCH_WIDTH_PX) / 2.0;
+        marks.push(Mark::Ruling((x, top), (x, bottom)));
+    }
+
+    // The digit rows print their digit in every column
+    for row in 2..ROWS {
+        let digit = (row - 2).to_string();
+        for col_idx in 0..COLUMNS {
+            marks.push(Mark::Figures(digit.clone(), DIGIT_SIZE, punch_centre(col_idx, row)));
+        }
+    }
+
+    // Column numbers, midway between rows 0 and 1 and below row 9
+    let below = |row: usize| FIRST_PUNCH_Y + row as f32 * ROW_SPACING + PUNCH_HEIGHT_PX + (ROW_SPACING - PUNCH_HEIGHT_PX) / 2.0;
+    for y_px in [below(2), below(ROWS - 1)] {
+        for col_idx in 0..COLUMNS {
+            let (x_px, _) = punch_centre(col_idx, 0);
+            marks.push(Mark::Figures((col_idx + 1).to_string(), COLUMN_NUMBER_SIZE, (x_px, y_px)));
+        }
+    }
+    marks
+}
+
+/// Operations drawing the blank card, in template pixels with the origin at
+/// bottom left; `rulings` are the columns (1-based) to rule a line after
+pub fn card_operations(rulings: &[usize]) -> Vec<(String, Vec<Object>)> {
+    let mut operations = vec![operation("RG", &INK_COLOUR)];
+    // Type size once the text object is begun
+    let mut text_size = None;
+    for mark in card_marks(rulings) {
+        match mark {
+            Mark::Outline(points) => {
+                operations.push(operation("rg", &CARD_COLOUR));
+                operations.push(operation("w", &[OUTLINE_WIDTH]));
+                let (x, y) = points[0];
+                operations.push(operation("m", &[x, flip(y)]));
+                for &(x, y) in &points[1..] {
+                    operations.push(operation("l", &[x, flip(y)]));
+                }
+                operations.push(operation("b", &[]));
+            }
+            Mark::Ruling((x1, y1), (x2, y2)) => {
+                operations.push(operation("w", &[RULING_WIDTH]));
+                operations.push(operation("m", &[x1, flip(y1)]));
+                operations.push(operation("l", &[x2, flip(y2)]));
+                operations.push(operation("S", &[]));
+            }
+            Mark::Figures(text, size, centre) => {
+                if text_size.is_none() {
+                    operations.push(operation("rg", &INK_COLOUR));
+                    operations.push(("BT".to_string(), vec![]));
+                }
+                if text_size != Some(size) {
+                    operations.push(("Tf".to_string(), vec![Object::Name(FONT.as_bytes().to_vec()), size.into()]));
+                    text_size = Some(size);
+                }
+                operations.extend(figures(&text, size, centre));
+            }
+        }
+    }
+    if text_size.is_some() {
+        operations.push(("ET".to_string(), vec![]));
+    }
+    operations
+}
+
+/// SVG colour for one of the card's colours
+fn svg_colour(colour: [f32; 3]) -> String {
+    let [r, g, b] = colour.map(|channel| (channel * 255.0).round() as u8);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// The blank card as SVG elements, in template pixels from the top left,
+/// drawn from the same marks as `card_operations`
+pub fn card_svg(rulings: &[usize]) -> String {
+    let ink = svg_colour(INK_COLOUR);
+    let mut svg = String::new();
+    let mut in_text = false;
+    for mark in card_marks(rulings) {
+        match mark {
+            Mark::Outline(points) => {
+                let points: Vec<String> = points.iter().map(|(x, y)| format!("{} {}", x, y)).collect();
+                svg.push_str(&format!(
+                    "<path d=\"M {} Z\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                    points.join(" L "),
+                    svg_colour(CARD_COLOUR),
+                    ink,
+                    OUTLINE_WIDTH
+                ));
+            }
+            Mark::Ruling((x1, y1), (x2, y2)) => {
+                svg.push_str(&format!(
+                    "<path d=\"M {} {} L {} {}\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                    x1, y1, x2, y2, ink, RULING_WIDTH
+                ));
+            }
+            // Figures are centred on their points as in the PDF
+            Mark::Figures(text, size, (x_px, y_px)) => {
+                if !in_text {
+                    svg.push_str(&format!(
+                        "<g font-family=\"Helvetica, Arial, sans-serif\" text-anchor=\"middle\" fill=\"{}\">\n",
+                        ink
+                    ));
+                    in_text = true;
+                }
+                let baseline = y_px + FIGURE_HEIGHT * size / 2.0;
+                svg.push_str(&format!("<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{}\">{}</text>\n", x_px, baseline, size, text));
+            }
+        }
+    }
+    if in_text {
+        svg.push_str("</g>\n");
+    }
+    svg
+}
+
+/// Distance from a point to a line segment
+fn distance_to_segment((x, y): (f32, f32), (x1, y1): (f32, f32), (x2, y2): (f32, f32)) -> f32 {
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let length_sq = dx * dx + dy * dy;
+    let along = if length_sq > 0.0 { (((x - x1) * dx + (y - y1) * dy) / length_sq).clamp(0.0, 1.0) } else { 0.0 };
+    (x - (x1 + along * dx)).hypot(y - (y1 + along * dy))
+}
+
+/// Whether a point lies inside a closed path, by the even-odd rule
+fn inside((x, y): (f32, f32), points: &[(f32, f32)]) -> bool {
+    let edges = points.iter().zip(points.iter().cycle().skip(1));
+    edges
+        .filter(|&(&(x1, y1), &(x2, y2))| (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1))
+        .count()
+        % 2
+        == 1
+}
+
+/// The blank card as an image of the given size, drawn from the same marks
+/// as `card_operations`
+pub fn card_image(rulings: &[usize], width: u32, height: u32) -> RgbImage {
+    let (scale_x, scale_y) = (width as f32 / WIDTH_PX, height as f32 / HEIGHT_PX);
+    let colour = |colour: [f32; 3]| Rgb(colour.map(|channel| (channel * 255.0).round() as u8));
+    let (stock, ink) = (colour(CARD_COLOUR), colour(INK_COLOUR));
+    // Template pixels at the middle of an image pixel
+    let at = |x: u32, y: u32| ((x as f32 + 0.5) / scale_x, (y as f32 + 0.5) / scale_y);
+    // Image pixels covering a span of template pixels
+    let pixels = |from: f32, to: f32, scale: f32, limit: u32| {
+        (from * scale).floor().max(0.0) as u32..((to * scale).ceil().max(0.0) as u32).min(limit)
+    };
+    // Lines are drawn at least a pixel wide
+    let reach = |line_width: f32| (line_width / 2.0).max(0.5 / scale_x.min(scale_y));
+
+    let mut image = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+    for mark in card_marks(rulings) {
+        match mark {
+            Mark::Outline(points) => {
+                let edges: Vec<((f32, f32), (f32, f32))> =
+                    points.iter().copied().zip(points.iter().copied().cycle().skip(1)).collect();
+                let reach = reach(OUTLINE_WIDTH);
+                for (x, y, pixel) in image.enumerate_pixels_mut() {
+                    let point = at(x, y);
+                    if edges.iter().any(|&(from, to)| distance_to_segment(point, from, to) <= reach) {
+                        *pixel = ink;
+                    } else if inside(point, &points) {
+                        *pixel = stock;
+                    }
+                }
+            }
+            Mark::Ruling(from, to) => {
+                let reach = reach(RULING_WIDTH);
+                for y in pixels(from.1.min(to.1) - reach, from.1.max(to.1) + reach, scale_y, height) {
+                    for x in pixels(from.0.min(to.0) - reach, from.0.max(to.0) + reach, scale_x, width) {
+                        if distance_to_segment(at(x, y), from, to) <= reach {
+                            image.put_pixel(x, y, ink);
+                        }
+                    }
+                }
+            }
+            Mark::Figures(text, size, (x_px, y_px)) => {
+                // Each figure is five dots wide with a dot's space after it
+                let (advance, figure_height) = (FIGURE_WIDTH * size, FIGURE_HEIGHT * size);
+                let (left, top) = (x_px - text.len() as f32 * advance / 2.0, y_px - figure_height / 2.0);
+                let digits: Vec<usize> = text.bytes().map(|byte| (byte - b'0') as usize).collect();
+                for y in pixels(top, top + figure_height, scale_y, height) {
+                    for x in pixels(left, left + digits.len() as f32 * advance, scale_x, width) {
+                        let (x_at, y_at) = at(x, y);
+                        let dot_column = ((x_at - left) / advance * 6.0).max(0.0) as usize;
+                        let dot_row = ((y_at - top) / figure_height * 7.0).max(0.0) as usize;
+                        let (figure, dot_column) = (dot_column / 6, dot_column % 6);
+                        let lit = digits.get(figure).is_some_and(|&digit| {
+                            dot_column < 5 && dot_row < 7 && FIGURE_DOTS[digit][dot_row] >> (4 - dot_column) & 1 == 1
+                        });
+                        if lit {
+                            image.put_pixel(x, y, ink);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pdf_and_svg_draw_the_same_card() {
+        let operations = card_operations(&[6, 72]);
+        let svg = card_svg(&[6, 72]);
+        let count = |operator: &str| operations.iter().filter(|(op, _)| op == operator).count();
+        // Ten digit rows and two rows of column numbers
+        assert_eq!(count("Tj"), 12 * COLUMNS);
+        assert_eq!(svg.matches("<text ").count(), 12 * COLUMNS);
+        assert_eq!(count("S"), 2);
+        assert_eq!(svg.matches("<path ").count(), 3);
+
+        // The same figure at the same place: the 0 in column 1
+        let (x, y) = punch_centre(0, 2);
+        let baseline = y + FIGURE_HEIGHT * DIGIT_SIZE / 2.0;
+        assert!(svg.contains(&format!("<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"8\">0</text>", x, baseline)));
+        let [Object::Real(left), Object::Real(bottom)] = operations.iter().find(|(op, _)| op == "Tm").unwrap().1[4..] else {
+            panic!("text matrix")
+        };
+        assert!((left - (x - FIGURE_WIDTH * DIGIT_SIZE / 2.0)).abs() < 0.01);
+        assert!((bottom - flip(baseline)).abs() < 0.01);
+    }
+
+    #[test]
+    fn image_prints_the_card() {
+        let image = card_image(&[], WIDTH_PX as u32, HEIGHT_PX as u32);
+        let stock = Rgb(CARD_COLOUR.map(|channel| (channel * 255.0).round() as u8));
+        let ink = Rgb(INK_COLOUR.map(|channel| (channel * 255.0).round() as u8));
+        // The corner is cut away, the card is stock between its columns
+        assert_eq!(*image.get_pixel(2, 2), Rgb([255, 255, 255]));
+        assert_eq!(*image.get_pixel(400, 4), stock);
+        let gap = (FIRST_PUNCH_X + COLUMN_SPACING - (COLUMN_SPACING - PUNCH_WIDTH_PX) / 2.0) as u32;
+        assert_eq!(*image.get_pixel(gap, 200), stock);
+        // The 8 of column 1 is inked across its middle dot row
+        let (x, y) = punch_centre(0, 10);
+        assert_eq!(*image.get_pixel(x as u32, y as u32), ink);
+    }
+}
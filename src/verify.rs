@@ -0,0 +1,272 @@
+// Reading the punches back out of a generated PDF, the way an IBM 056
+// verifier re-keyed a deck against the original: every card's template
+// image (or drawn card) marks where the card sits on the page, and every
+// filled rectangle drawn after it is one punch, mapped back to column and
+// row through the same template geometry the cards were drawn with.
+
+use lopdf::content::Content;
+use lopdf::{Document, Object, ObjectId};
+
+use crate::calibration::Calibration;
+use crate::layout::cut_and_stack_index;
+use crate::{PunchCard, COLUMNS, ROWS};
+
+/// How far a rectangle may sit from a grid position, as a fraction of the
+/// column or row spacing, before it counts as misplaced
+const GRID_TOLERANCE: f32 = 0.25;
+
+/// Where a card's template image is drawn on the page
+struct CardFrame {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    /// Template image size in pixels, the units of the punch grid
+    image_width: f32,
+    image_height: f32,
+    /// The punch grid the card was drawn with
+    grid: Calibration,
+}
+
+impl CardFrame {
+    /// Column and row of a punch rectangle given in page coordinates
+    fn grid_position(&self, x: f32, y: f32, height: f32) -> Option<(usize, usize)> {
+        let scale_x = self.width / self.image_width;
+        let scale_y = self.height / self.image_height;
+
+        // Template images run top down, PDF pages bottom up
+        let punch_x_px = (x - self.x) / scale_x;
+        let punch_y_px = self.image_height - (y + height - self.y) / scale_y;
+
+        let col = (punch_x_px - self.grid.first_punch_x) / self.grid.column_spacing;
+        let row = (punch_y_px - self.grid.first_punch_y) / self.grid.row_spacing;
+        let on_grid = |pos: f32, limit: usize| {
+            (pos.round() - pos).abs() <= GRID_TOLERANCE && pos.round() >= 0.0 && (pos.round() as usize) < limit
+        };
+        (on_grid(col, COLUMNS) && on_grid(row, ROWS)).then(|| (col.round() as usize, row.round() as usize))
+    }
+}
+
+/// Size in pixels of the card background a page draws under the given name:
+/// the template image, or the form a vector-drawn card is made of, which
+/// says so with a third value of true
+fn image_size(doc: &Document, page_id: ObjectId, name: &[u8]) -> Option<(f32, f32, bool)> {
+    let (resources, _) = doc.get_page_resources(page_id);
+    let xobject_id = resources?
+        .get(b"XObject")
+        .and_then(Object::as_dict)
+        .and_then(|xobjects| xobjects.get(name))
+        .and_then(Object::as_reference)
+        .ok()?;
+    let image = doc.get_object(xobject_id).and_then(Object::as_stream).ok()?;
+    if let Ok(bbox) = image.dict.get(b"BBox").and_then(Object::as_array) {
+        let corner = |idx: usize| bbox.get(idx).and_then(|n| n.as_float().ok());
+        return Some((corner(2)? - corner(0)?, corner(3)? - corner(1)?, true));
+    }
+    let dimension = |key: &[u8]| image.dict.get(key).and_then(Object::as_float).ok();
+    Some((dimension(b"Width")?, dimension(b"Height")?, false))
+}
+
+/// Read every card's punches from a PDF made by `generate_punch_card_pdf`,
+/// page by page. Cards on the template image are read through its punch
+/// grid; drawn cards always have the bundled template's.
+pub fn read_pdf(path: &str, template_grid: &Calibration) -> Result<Vec<Vec<PunchCard>>, String> {
+    let doc = Document::load(path).map_err(|err| format!("cannot read PDF: {}", err))?;
+    let mut pages = Vec::new();
+
+    for (page_num, page_id) in doc.get_pages() {
+        let mut cards = Vec::new();
+        let data = doc
+            .get_page_content(page_id)
+            .map_err(|err| format!("page {}: {}", page_num, err))?;
+        let content = Content::decode(&data).map_err(|err| format!("page {}: {}", page_num, err))?;
+
+        let mut placement: Option<[f32; 6]> = None;
+        let mut frame: Option<CardFrame> = None;
+        for operation in &content.operations {
+            let numbers: Vec<f32> = operation.operands.iter().filter_map(|op| op.as_float().ok()).collect();
+            match operation.operator.as_str() {
+                "cm" => placement = numbers.try_into().ok(),
+                "Do" => {
+                    // Each card starts with its template image
+                    let name = operation.operands.first().and_then(|op| op.as_name().ok()).unwrap_or_default();
+                    let (image_width, image_height, drawn) = image_size(&doc, page_id, name)
+                        .ok_or_else(|| format!("page {}: card image has no size", page_num))?;
+                    let [width, _, _, height, x, y] =
+                        placement.ok_or_else(|| format!("page {}: card image is drawn without a position", page_num))?;
+                    let grid = if drawn { Calibration::BUNDLED } else { template_grid.clone() };
+                    frame = Some(CardFrame { x, y, width, height, image_width, image_height, grid });
+                    cards.push(PunchCard::new());
+                }
+                "re" => {
+                    let card_num = cards.len();
+                    let (Some(frame), Some(card)) = (&frame, cards.last_mut()) else {
+                        return Err(format!("page {}: punch drawn before any card", page_num));
+                    };
+                    let [x, y, _, height] = numbers[..] else {
+                        return Err(format!("card {}: malformed rectangle", card_num));
+                    };
+                    let (col, row) = frame.grid_position(x, y, height).ok_or_else(|| {
+                        format!("card {}: rectangle at ({:.1}, {:.1}) is off the punch grid", card_num, x, y)
+                    })?;
+                    if !card.columns[col].contains(&row) {
+                        card.columns[col].push(row);
+                    }
+                }
+                _ => {}
+            }
+        }
+        pages.push(cards);
+    }
+
+    for card in pages.iter_mut().flatten() {
+        for punches in &mut card.columns {
+            punches.sort();
+        }
+    }
+    Ok(pages)
+}
+
+/// Put the cards read from each page back in deck order. Pages printed for
+/// cut and stack hold every so many cards of the deck, as many as there are
+/// pages apart.
+pub fn deck_order(pages: Vec<Vec<PunchCard>>, cut_and_stack: bool) -> Vec<PunchCard> {
+    if !cut_and_stack {
+        return pages.into_iter().flatten().collect();
+    }
+    let sheets = pages.len();
+    let mut placed: Vec<(usize, PunchCard)> = pages
+        .into_iter()
+        .enumerate()
+        .flat_map(|(sheet, cards)| {
+            cards
+                .into_iter()
+                .enumerate()
+                .map(move |(position, card)| (cut_and_stack_index(sheets, sheet, position), card))
+        })
+        .collect();
+    placed.sort_by_key(|&(card_idx, _)| card_idx);
+    placed.into_iter().map(|(_, card)| card).collect()
+}
+
+/// A column whose punches differ from what was meant
+pub struct Mismatch {
+    /// 1-based card and column
+    pub card: usize,
+    pub column: usize,
+    pub expected: Vec<usize>,
+    pub punched: Vec<usize>,
+}
+
+/// Compare the punched deck column by column against the expected one
+pub fn compare(expected: &[PunchCard], punched: &[PunchCard]) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    for (card_idx, (want, got)) in expected.iter().zip(punched).enumerate() {
+        for (col_idx, (want_rows, got_rows)) in want.columns.iter().zip(&got.columns).enumerate() {
+            let mut want_rows = want_rows.clone();
+            want_rows.sort();
+            if want_rows != *got_rows {
+                mismatches.push(Mismatch {
+                    card: card_idx + 1,
+                    column: col_idx + 1,
+                    expected: want_rows,
+                    punched: got_rows.clone(),
+                });
+            }
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::{Layout, PageSize};
+    use crate::printer::PrinterProfile;
+    use crate::{calibration, generate_punch_card_pdf, get_hollerith_encoding, read_coding_sheet, Deck, PdfOptions};
+
+    /// A card carrying its place in the deck in its first two columns
+    fn numbered_card(card_idx: usize) -> PunchCard {
+        let mut card = PunchCard::new();
+        card.columns[0] = vec![card_idx % ROWS];
+        card.columns[1] = vec![card_idx / ROWS];
+        card
+    }
+
+    #[test]
+    fn imposed_pages_read_back_in_deck_order() {
+        for cut_and_stack in [false, true] {
+            for count in [1, 3, 7, 10, 12] {
+                let mut layout = Layout::new(PageSize::A4, Some(3), None, 0.0, false).unwrap();
+                layout.cut_and_stack = cut_and_stack;
+                let pages: Vec<Vec<PunchCard>> = layout
+                    .impose(count)
+                    .iter()
+                    .map(|page| page.iter().map(|&card_idx| numbered_card(card_idx)).collect())
+                    .collect();
+                assert_eq!(pages.len(), count.div_ceil(3));
+
+                let deck: Vec<Vec<Vec<usize>>> =
+                    deck_order(pages, cut_and_stack).into_iter().map(|card| card.columns).collect();
+                let expected: Vec<Vec<Vec<usize>>> = (0..count).map(|card_idx| numbered_card(card_idx).columns).collect();
+                assert_eq!(deck, expected, "{} cards, cut and stack {}", count, cut_and_stack);
+            }
+        }
+    }
+
+    #[test]
+    fn cut_and_stack_piles_follow_the_deck() {
+        let mut layout = Layout::new(PageSize::A4, Some(3), None, 0.0, false).unwrap();
+        layout.cut_and_stack = true;
+        // The first pile holds the first card of every sheet, the next pile
+        // the second, and so on
+        assert_eq!(layout.impose(10), vec![vec![0, 4, 8], vec![1, 5, 9], vec![2, 6], vec![3, 7]]);
+    }
+
+    #[test]
+    fn generated_pdf_and_coding_sheet_read_back() {
+        let encoding_map = get_hollerith_encoding();
+        let lines = [
+            "       IDENTIFICATION DIVISION.",
+            "       PROGRAM-ID. ROUND.",
+            "       PROCEDURE DIVISION.",
+            "           DISPLAY 'A+B=C, (1*2)/3 $4.5;'.",
+            "           STOP RUN.",
+        ];
+        let mut deck = Deck::new();
+        deck.push_program("ROUND", &lines.map(String::from), 1);
+        let expected = deck.punch_cards(&encoding_map);
+
+        let dir = std::env::temp_dir().join(format!("punchcard-verify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (pdf, sheet) = (dir.join("deck.pdf"), dir.join("deck.txt"));
+        let (pdf, sheet) = (pdf.to_str().unwrap(), sheet.to_str().unwrap());
+        // Each card is drawn under its place on the page and the printer's
+        // correction, so only the card's own cm is read
+        for vector_card in [false, true] {
+            let options = PdfOptions {
+                interpret: true,
+                interpreter_lines: Vec::new(),
+                vector_card,
+                rulings: Vec::new(),
+                printer: PrinterProfile { offset_x_mm: 1.5, offset_y_mm: -0.5, scale_x: 1.01, scale_y: 0.99, rotation_deg: 0.3 },
+                layout: Layout::new(PageSize::A4, Some(2), None, 0.0, false).unwrap(),
+                crop_marks: true,
+                cut_contour: false,
+                cut_contour_outline: false,
+                corner_radius_mm: 0.0,
+            };
+            generate_punch_card_pdf(&deck, "punchcard_template.png", pdf, sheet, &options).unwrap();
+            let pages = read_pdf(pdf, &calibration::load("punchcard_template.png").unwrap()).unwrap();
+            assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2, 1]);
+            let punched = deck_order(pages, false);
+            assert_eq!(punched.len(), expected.len());
+            assert!(compare(&expected, &punched).is_empty(), "vector card {}", vector_card);
+        }
+
+        let cards = read_coding_sheet(&std::fs::read_to_string(sheet).unwrap(), &encoding_map).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(cards.len(), expected.len());
+        assert!(compare(&expected, &cards).is_empty());
+    }
+}